@@ -0,0 +1,94 @@
+//! Compares `format_url` and `write_url` with the string-building implementation of 0.6, and
+//! `write_url` with a configuration it can't stream.
+
+use std::fmt::Write;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use format_url::{FormatUrl, JoinMode, Template};
+
+const BASE: &str = "https://api.example.com/";
+const PATH_TEMPLATE: &str = "/repos/:owner/:repo/issues/:number";
+const SUBSTITUTES: [(&str, &str); 3] = [
+    ("owner", "alextes"),
+    ("repo", "format url"),
+    ("number", "42"),
+];
+const QUERY_PARAMS: [(&str, &str); 4] = [
+    ("state", "open"),
+    ("labels", "bug,help wanted"),
+    ("sort", "created"),
+    ("per_page", "100"),
+];
+
+fn format_url(c: &mut Criterion) {
+    let mut group = c.benchmark_group("format_url");
+
+    group.bench_function("0.6", |b| {
+        b.iter(|| {
+            format_url_0_6::FormatUrl::new(black_box(BASE))
+                .with_path_template(PATH_TEMPLATE)
+                .with_substitutes(SUBSTITUTES.to_vec())
+                .with_query_params(QUERY_PARAMS.to_vec())
+                .format_url()
+        })
+    });
+
+    group.bench_function("format_url", |b| {
+        b.iter(|| {
+            FormatUrl::new(black_box(BASE))
+                .with_path_template(PATH_TEMPLATE)
+                .with_substitutes(SUBSTITUTES)
+                .with_query_params(QUERY_PARAMS.to_vec())
+                .format_url()
+        })
+    });
+
+    // A parsed template and a reused buffer, as a proxy formatting many URLs would use.
+    let template = Template::parse(PATH_TEMPLATE);
+    let mut out = String::with_capacity(256);
+    group.bench_function("write_url", |b| {
+        b.iter(|| {
+            out.clear();
+            FormatUrl::new(black_box(BASE))
+                .with_path_template(&template)
+                .with_substitutes(SUBSTITUTES)
+                .with_query_params(QUERY_PARAMS.to_vec())
+                .write_url(&mut out)
+                .unwrap();
+            black_box(&out);
+        })
+    });
+
+    // Resolving the path can't be streamed, so this goes through a `String` first.
+    group.bench_function("write_url resolve", |b| {
+        b.iter(|| {
+            out.clear();
+            FormatUrl::new(black_box(BASE))
+                .with_path_template(&template)
+                .with_join_mode(JoinMode::Resolve)
+                .with_substitutes(SUBSTITUTES)
+                .with_query_params(QUERY_PARAMS.to_vec())
+                .write_url(&mut out)
+                .unwrap();
+            black_box(&out);
+        })
+    });
+
+    group.bench_function("display", |b| {
+        b.iter(|| {
+            out.clear();
+            let url = FormatUrl::new(black_box(BASE))
+                .with_path_template(&template)
+                .with_substitutes(SUBSTITUTES)
+                .with_query_params(QUERY_PARAMS.to_vec());
+            write!(out, "{url}").unwrap();
+            black_box(&out);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, format_url);
+criterion_main!(benches);
@@ -0,0 +1,313 @@
+//! The path template grammar of [format-url](https://docs.rs/format-url), shared with its macros
+//! so both read a template the same way. Use `format_url::Template` rather than depending on this
+//! crate directly.
+
+use std::iter::{FusedIterator, Peekable};
+use std::str::CharIndices;
+
+/// A piece of a path template, in the order written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token<'t> {
+    /// A literal character, with its escaping backslash removed.
+    Literal(char),
+    /// The `(` of a `(/...)` group.
+    OpenGroup,
+    /// The `)` closing a group.
+    CloseGroup,
+    Placeholder(Placeholder<'t>),
+}
+
+/// A placeholder, as in `:id`, `*path(\/.+)`, `{id:uuid?}` or `{+path}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Placeholder<'t> {
+    pub name: &'t str,
+    /// The byte offset of the placeholder in the template.
+    pub position: usize,
+    /// The placeholder as written, its modifier included.
+    pub written: &'t str,
+    /// Written `*name` or `{+name}`.
+    pub splat: bool,
+    pub constraint: Option<Constraint<'t>>,
+    pub modifier: Modifier<'t>,
+}
+
+/// What a placeholder says it accepts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constraint<'t> {
+    /// `{name:constraint}`
+    Named(&'t str),
+    /// `:name(pattern)`, without the parentheses.
+    Pattern(&'t str),
+}
+
+/// How a placeholder ends, after its name and constraint.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modifier<'t> {
+    None,
+    /// `?`
+    Optional,
+    /// `=default`
+    Default(&'t str),
+}
+
+/// Why a template can't be read, and the byte offset where that became clear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Error {
+    pub reason: &'static str,
+    pub position: usize,
+}
+
+/// Whether `c` may be part of a placeholder name.
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Whether `c` may be part of a default value, which is limited to the unreserved characters.
+fn is_default_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')
+}
+
+/// Whether a backslash before `c` escapes it. Any other backslash is kept as is.
+fn is_escapable(c: char) -> bool {
+    matches!(c, '\\' | ':' | '*' | '{' | '}' | '(' | ')' | '?' | '=')
+}
+
+/// Splits a path template into [`Token`]s. `:name` and `*name` placeholders are recognized with
+/// `colon`, `{name}` and `{+name}` ones with `braces`. Stops after the first [`Error`].
+pub struct Tokens<'t> {
+    template: &'t str,
+    chars: Peekable<CharIndices<'t>>,
+    colon: bool,
+    braces: bool,
+    in_group: bool,
+    failed: bool,
+}
+
+impl<'t> Tokens<'t> {
+    pub fn new(template: &'t str, colon: bool, braces: bool) -> Self {
+        Self {
+            template,
+            chars: template.char_indices().peekable(),
+            colon,
+            braces,
+            in_group: false,
+            failed: false,
+        }
+    }
+
+    /// Consume the longest run of name characters starting at `start` and return it.
+    fn take_name(&mut self, start: usize) -> &'t str {
+        let mut end = start;
+        while let Some((index, c)) = self.chars.next_if(|&(_, c)| is_name_char(c)) {
+            end = index + c.len_utf8();
+        }
+        &self.template[start..end]
+    }
+
+    /// Consume a `(pattern)` at `start`, if there is one with a matching `)`, and return the
+    /// pattern. Backslashes in the pattern escape the next character. A `(` followed by a `/`
+    /// opens a group instead, as in `/orgs/:org(/:project)`.
+    fn take_pattern(&mut self, start: usize) -> Option<&'t str> {
+        let pattern = self.template[start..]
+            .strip_prefix('(')
+            .filter(|pattern| !pattern.starts_with('/'))?;
+        let mut depth = 0;
+        let mut pattern_chars = pattern.char_indices();
+        let len = loop {
+            match pattern_chars.next()? {
+                (_, '\\') => {
+                    pattern_chars.next();
+                }
+                (_, '(') => depth += 1,
+                (len, ')') if depth == 0 => break len,
+                (_, ')') => depth -= 1,
+                _ => {}
+            }
+        };
+        let end = start + 1 + len + 1;
+        while self.chars.next_if(|&(index, _)| index < end).is_some() {}
+        Some(&pattern[..len])
+    }
+
+    /// Consume a `?` or `=default` at `start`. A `:name?` is only optional when the `?` ends a
+    /// segment, otherwise it starts the query.
+    fn take_modifier(&mut self, start: usize, braced: bool) -> Modifier<'t> {
+        let rest = &self.template[start..];
+        if let Some(after) = rest.strip_prefix('?') {
+            let ends_segment = match after.chars().next() {
+                None | Some('/' | ')') => !braced,
+                Some('}') => braced,
+                _ => false,
+            };
+            if ends_segment {
+                self.chars.next();
+                return Modifier::Optional;
+            }
+        } else if let Some(after) = rest.strip_prefix('=') {
+            let default_len = after.find(|c| !is_default_char(c)).unwrap_or(after.len());
+            if default_len > 0 {
+                let end = start + 1 + default_len;
+                while self.chars.next_if(|&(index, _)| index < end).is_some() {}
+                return Modifier::Default(&after[..default_len]);
+            }
+        }
+        Modifier::None
+    }
+
+    /// The rest of a `:name` or `*name` placeholder starting at `index`, or `None` when no name
+    /// follows.
+    fn colon_placeholder(&mut self, index: usize, c: char) -> Option<Placeholder<'t>> {
+        let name = self.take_name(index + 1);
+        if name.is_empty() {
+            return None;
+        }
+        let mut end = index + 1 + name.len();
+        let constraint = self.take_pattern(end).map(|pattern| {
+            end += pattern.len() + 2;
+            Constraint::Pattern(pattern)
+        });
+        let modifier = self.take_modifier(end, false);
+        Some(self.placeholder(index, name, c == '*', constraint, modifier))
+    }
+
+    /// The rest of a `{name}` or `{+name}` placeholder starting at `index`.
+    fn braced_placeholder(&mut self, index: usize) -> Result<Placeholder<'t>, Error> {
+        let splat = self.chars.next_if(|&(_, next)| next == '+').is_some();
+        let start = index + 1 + usize::from(splat);
+        let name = self.take_name(start);
+        let mut end = start + name.len();
+        let constraint = match self.chars.next_if(|&(_, next)| next == ':') {
+            Some(_) => {
+                let constraint = self.take_name(end + 1);
+                end += 1 + constraint.len();
+                Some(constraint)
+            }
+            None => None,
+        };
+        let modifier = self.take_modifier(end, true);
+        match self.chars.next() {
+            Some((_, '}')) if !name.is_empty() && constraint != Some("") => {
+                let constraint = constraint.map(Constraint::Named);
+                Ok(self.placeholder(index, name, splat, constraint, modifier))
+            }
+            _ => Err(Error {
+                reason: "expected a placeholder name, optionally followed by ':constraint' and \
+                         '?' or '=default', and '}'",
+                position: index,
+            }),
+        }
+    }
+
+    fn placeholder(
+        &mut self,
+        index: usize,
+        name: &'t str,
+        splat: bool,
+        constraint: Option<Constraint<'t>>,
+        modifier: Modifier<'t>,
+    ) -> Placeholder<'t> {
+        let written = match self.chars.peek() {
+            Some(&(end, _)) => &self.template[index..end],
+            None => &self.template[index..],
+        };
+        Placeholder {
+            name,
+            position: index,
+            written,
+            splat,
+            constraint,
+            modifier,
+        }
+    }
+}
+
+impl<'t> Iterator for Tokens<'t> {
+    type Item = Result<Token<'t>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let (index, c) = self.chars.next()?;
+        let token = match c {
+            '\\' => match self.chars.next_if(|&(_, next)| is_escapable(next)) {
+                Some((_, next)) => Token::Literal(next),
+                None => Token::Literal(c),
+            },
+            '(' if !self.in_group
+                && self.template[index + 1..].starts_with('/')
+                && closes_group(&self.template[index + 1..]) =>
+            {
+                self.in_group = true;
+                Token::OpenGroup
+            }
+            ')' if self.in_group => {
+                self.in_group = false;
+                Token::CloseGroup
+            }
+            ':' | '*' if self.colon => match self.colon_placeholder(index, c) {
+                Some(placeholder) => Token::Placeholder(placeholder),
+                None => Token::Literal(c),
+            },
+            '{' if self.braces => match self.braced_placeholder(index) {
+                Ok(placeholder) => Token::Placeholder(placeholder),
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(error));
+                }
+            },
+            '}' if self.braces => {
+                self.failed = true;
+                return Some(Err(Error {
+                    reason: "unmatched '}', escape it as '\\}'",
+                    position: index,
+                }));
+            }
+            _ => Token::Literal(c),
+        };
+        Some(Ok(token))
+    }
+}
+
+impl FusedIterator for Tokens<'_> {}
+
+/// Whether `rest` holds a `)` that isn't escaped, closing a group opened just before it.
+fn closes_group(rest: &str) -> bool {
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            ')' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// A placeholder in a path template using the default `:name` syntax, and whether the path can
+/// leave it out: it's in a `(/...)` group, marked `?` or has a default.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PathPlaceholder<'t> {
+    pub name: &'t str,
+    pub optional: bool,
+}
+
+/// The placeholders in a path template using the default `:name` syntax.
+pub fn path_placeholders(template: &str) -> Vec<PathPlaceholder<'_>> {
+    let mut placeholders = Vec::new();
+    let mut in_group = false;
+    for token in Tokens::new(template, true, false).flatten() {
+        match token {
+            Token::OpenGroup => in_group = true,
+            Token::CloseGroup => in_group = false,
+            Token::Placeholder(placeholder) => placeholders.push(PathPlaceholder {
+                name: placeholder.name,
+                optional: in_group || placeholder.modifier != Modifier::None,
+            }),
+            Token::Literal(_) => {}
+        }
+    }
+    placeholders
+}
@@ -0,0 +1,240 @@
+//! `#[derive(Endpoint)]`.
+
+use format_url_grammar::path_placeholders;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Fields, LitStr, Type};
+
+/// How a field ends up in the URL.
+enum Kind {
+    Path,
+    Query,
+}
+
+struct Field<'f> {
+    field: &'f syn::Field,
+    kind: Kind,
+    /// The placeholder or query parameter name.
+    name: String,
+}
+
+/// Parse `#[endpoint(path)]` or `#[endpoint(query)]` on a field, optionally followed by
+/// `rename = "..."`.
+fn parse_field(field: &syn::Field) -> syn::Result<Option<Field<'_>>> {
+    let ident = field.ident.as_ref().expect("named fields have an ident");
+    let mut kind = None;
+    let mut name = ident.to_string().trim_start_matches("r#").to_string();
+
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("endpoint"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let parsed = if meta.path.is_ident("path") {
+                Kind::Path
+            } else if meta.path.is_ident("query") {
+                Kind::Query
+            } else if meta.path.is_ident("rename") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+                return Ok(());
+            } else {
+                return Err(meta.error("expected `path`, `query` or `rename = \"...\"`"));
+            };
+            if kind.is_some() {
+                return Err(meta.error("a field is either `path` or `query`"));
+            }
+            kind = Some(parsed);
+            Ok(())
+        })?;
+    }
+
+    Ok(kind.map(|kind| Field { field, kind, name }))
+}
+
+/// The name of the outermost type if it's one of `Option` or `Vec`, however it's written.
+fn wrapper(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .filter(|ident| ident == "Option" || ident == "Vec"),
+        _ => None,
+    }
+}
+
+/// Read `#[endpoint(path = "...")]` off the struct.
+fn parse_path_template(input: &DeriveInput) -> syn::Result<LitStr> {
+    let mut template = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("endpoint"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("path") {
+                template = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `path = \"...\"`"))
+            }
+        })?;
+    }
+    template.ok_or_else(|| {
+        syn::Error::new(
+            input.ident.span(),
+            "missing #[endpoint(path = \"...\")] attribute",
+        )
+    })
+}
+
+pub(crate) fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let template = parse_path_template(&input)?;
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "Endpoint can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Endpoint can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut path_fields = Vec::new();
+    let mut query_fields = Vec::new();
+    for field in fields {
+        match parse_field(field)? {
+            Some(
+                field @ Field {
+                    kind: Kind::Path, ..
+                },
+            ) => path_fields.push(field),
+            Some(
+                field @ Field {
+                    kind: Kind::Query, ..
+                },
+            ) => query_fields.push(field),
+            None => {}
+        }
+    }
+
+    let template_value = template.value();
+    let placeholders = path_placeholders(&template_value);
+    for placeholder in &placeholders {
+        if !path_fields
+            .iter()
+            .any(|field| field.name == placeholder.name)
+        {
+            return Err(syn::Error::new(
+                template.span(),
+                format!(
+                    "placeholder `:{}` has no #[endpoint(path)] field",
+                    placeholder.name
+                ),
+            ));
+        }
+    }
+    for field in &path_fields {
+        let matching = placeholders
+            .iter()
+            .filter(|placeholder| placeholder.name == field.name);
+        let is_option = wrapper(&field.field.ty).as_deref() == Some("Option");
+        if is_option && matching.clone().any(|placeholder| !placeholder.optional) {
+            return Err(syn::Error::new(
+                field.field.span(),
+                format!(
+                    "#[endpoint(path)] field `{}` is an Option, but `:{}` is always in the path, \
+                     put it in a `(/...)` group or mark it `?` or `=default`",
+                    field
+                        .field
+                        .ident
+                        .as_ref()
+                        .expect("named fields have an ident"),
+                    field.name
+                ),
+            ));
+        }
+        if matching.count() == 0 {
+            return Err(syn::Error::new(
+                field.field.span(),
+                format!(
+                    "#[endpoint(path)] field `{}` has no `:{}` placeholder in the path template",
+                    field
+                        .field
+                        .ident
+                        .as_ref()
+                        .expect("named fields have an ident"),
+                    field.name
+                ),
+            ));
+        }
+    }
+
+    let substitute_pushes = path_fields.iter().map(|Field { field, name, .. }| {
+        let ident = &field.ident;
+        match wrapper(&field.ty).as_deref() {
+            Some("Option") => quote! {
+                if let ::std::option::Option::Some(value) = &self.#ident {
+                    substitutes.push((#name, value as &dyn ::format_url::UrlValue));
+                }
+            },
+            _ => quote!(substitutes.push((#name, &self.#ident as &dyn ::format_url::UrlValue));),
+        }
+    });
+    let with_substitutes = (!path_fields.is_empty()).then(|| {
+        quote! {
+            .with_substitutes({
+                let mut substitutes: ::std::vec::Vec<(&str, &dyn ::format_url::UrlValue)> =
+                    ::std::vec::Vec::new();
+                #(#substitute_pushes)*
+                substitutes
+            })
+        }
+    });
+
+    let query_pushes = query_fields.iter().map(|Field { field, name, .. }| {
+        let ident = &field.ident;
+        match wrapper(&field.ty).as_deref() {
+            Some("Option") => quote! {
+                if let ::std::option::Option::Some(value) = &self.#ident {
+                    query.push(#name, value);
+                }
+            },
+            Some("Vec") => quote! {
+                query.push_seq(#name, self.#ident.iter().map(|value| value as &dyn ::format_url::UrlValue));
+            },
+            _ => quote!(query.push(#name, &self.#ident);),
+        }
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::format_url::Endpoint for #ident #ty_generics #where_clause {
+            fn to_format_url<'format_url>(
+                &'format_url self,
+                base: &'format_url str,
+            ) -> ::format_url::FormatUrl<'format_url> {
+                let mut query = ::format_url::__private::Query::default();
+                #(#query_pushes)*
+                let url = ::format_url::FormatUrl::new(base)
+                    .with_path_template(#template)
+                    #with_substitutes;
+                query.apply(url)
+            }
+        }
+    })
+}
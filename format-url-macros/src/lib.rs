@@ -0,0 +1,149 @@
+//! Procedural macros for [format-url](https://docs.rs/format-url). Use them through the `macros`
+//! feature of that crate rather than depending on this crate directly.
+
+mod endpoint;
+mod template;
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, DeriveInput, Expr, Ident, LitStr, Token};
+
+use template::Piece;
+
+/// `name = value`
+struct Argument {
+    name: Ident,
+    value: Expr,
+}
+
+impl Parse for Argument {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let value = input.parse()?;
+        Ok(Argument { name, value })
+    }
+}
+
+struct FormatUrlInput {
+    template: LitStr,
+    arguments: Punctuated<Argument, Token![,]>,
+}
+
+impl Parse for FormatUrlInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let template = input.parse()?;
+        let arguments = if input.is_empty() {
+            Punctuated::new()
+        } else {
+            input.parse::<Token![,]>()?;
+            Punctuated::parse_terminated(input)?
+        };
+        Ok(FormatUrlInput {
+            template,
+            arguments,
+        })
+    }
+}
+
+/// See `format_url::format_url!`.
+#[proc_macro]
+pub fn format_url(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as FormatUrlInput);
+    expand_format_url(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// See `format_url::Endpoint`.
+#[proc_macro_derive(Endpoint, attributes(endpoint))]
+pub fn derive_endpoint(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    endpoint::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_format_url(input: FormatUrlInput) -> syn::Result<proc_macro2::TokenStream> {
+    let pieces = template::parse(&input.template.value())
+        .map_err(|reason| syn::Error::new(input.template.span(), reason))?;
+
+    let mut names = BTreeSet::new();
+    for argument in &input.arguments {
+        let name = argument.name.to_string();
+        if !names.insert(name.clone()) {
+            return Err(syn::Error::new(
+                argument.name.span(),
+                format!("duplicate argument named `{name}`"),
+            ));
+        }
+    }
+
+    for argument in &input.arguments {
+        let used = pieces
+            .iter()
+            .any(|piece| matches!(piece, Piece::Placeholder { name, .. } if argument.name == name));
+        if !used {
+            return Err(syn::Error::new(
+                argument.name.span(),
+                format!("argument `{}` is never used in the template", argument.name),
+            ));
+        }
+    }
+
+    // Evaluate every argument once, in the order given, before building the URL. Placeholders
+    // without an argument capture the variable of that name, like `format!` does.
+    let mut bindings = Vec::new();
+    let mut bound = BTreeMap::new();
+    for argument in &input.arguments {
+        let binding = format_ident!("arg_{}", argument.name, span = Span::mixed_site());
+        let value = &argument.value;
+        bindings.push(quote!(let #binding = &#value;));
+        bound.insert(argument.name.to_string(), binding);
+    }
+    for piece in &pieces {
+        if let Piece::Placeholder { name, .. } = piece {
+            if !bound.contains_key(name) {
+                let binding = format_ident!("arg_{}", name, span = Span::mixed_site());
+                let captured = Ident::new(name, input.template.span());
+                bindings.push(quote!(let #binding = &#captured;));
+                bound.insert(name.clone(), binding);
+            }
+        }
+    }
+
+    let capacity: usize = pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Literal(literal) => literal.len(),
+            Piece::Placeholder { .. } => 0,
+        })
+        .sum();
+
+    let url = Ident::new("url", Span::mixed_site());
+    let pushes = pieces.iter().map(|piece| match piece {
+        Piece::Literal(literal) => quote!(#url.push_str(#literal);),
+        Piece::Placeholder { name, in_query } => {
+            let value = &bound[name];
+            if *in_query {
+                quote!(::format_url::__private::push_query(&mut #url, #value);)
+            } else {
+                quote!(::format_url::__private::push_path(&mut #url, #value);)
+            }
+        }
+    });
+
+    Ok(quote! {
+        {
+            #(#bindings)*
+            let mut #url = ::std::string::String::with_capacity(#capacity);
+            #(#pushes)*
+            #url
+        }
+    })
+}
@@ -0,0 +1,123 @@
+//! Parsing `format_url!` templates.
+
+/// A piece of a template, in order.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Piece {
+    Literal(String),
+    /// `{name}`, with `in_query` set when it comes after the `?`.
+    Placeholder {
+        name: String,
+        in_query: bool,
+    },
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Parse a template like `https://api.example.com/user/{id}?active={active}`. Like `format!`,
+/// `{{` and `}}` stand for literal braces.
+pub(crate) fn parse(template: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut in_query = false;
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => return Err("unclosed `{` in template".to_string()),
+                    }
+                }
+                if name.is_empty() {
+                    return Err(
+                        "positional placeholders aren't supported, name it like `{id}`".to_string(),
+                    );
+                }
+                if !is_valid_name(&name) {
+                    return Err(format!("invalid placeholder name `{name}`"));
+                }
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(Piece::Placeholder { name, in_query });
+            }
+            '}' => {
+                return Err("unmatched `}` in template, write `}}` for a literal `}`".to_string())
+            }
+            c if c.is_whitespace() || c.is_control() => {
+                return Err(format!(
+                    "URLs can't contain {c:?}, percent-encode it or pass it as an argument"
+                ))
+            }
+            c => {
+                in_query |= c == '?';
+                literal.push(c);
+            }
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+    Ok(pieces)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::template::{parse, Piece};
+
+    fn literal(value: &str) -> Piece {
+        Piece::Literal(value.to_string())
+    }
+
+    fn placeholder(name: &str, in_query: bool) -> Piece {
+        Piece::Placeholder {
+            name: name.to_string(),
+            in_query,
+        }
+    }
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            parse("https://api.x.com/user/{id}?active={active}"),
+            Ok(vec![
+                literal("https://api.x.com/user/"),
+                placeholder("id", false),
+                literal("?active="),
+                placeholder("active", true),
+            ])
+        );
+    }
+
+    #[test]
+    fn escaped_braces_test() {
+        assert_eq!(
+            parse("/a{{b}}/{id}"),
+            Ok(vec![literal("/a{b}/"), placeholder("id", false)])
+        );
+    }
+
+    #[test]
+    fn malformed_test() {
+        for template in ["/{id", "/{}", "/{1d}", "/{a-b}", "/a}", "/a b"] {
+            assert!(parse(template).is_err(), "{template}");
+        }
+    }
+}
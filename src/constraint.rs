@@ -0,0 +1,236 @@
+//! Checks placeholders put on their substitutes, like `{id:uuid}` or `:id(\d+)`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+type Check = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Named checks placeholders can refer to, as in `{id:uuid}`. On top of the checks registered
+/// here, these are always available:
+///
+/// * `integer`: digits, optionally preceded by a `-`.
+/// * `uuid`: a UUID in its hyphenated form, such as `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+/// * `slug`: lowercase ASCII letters and digits, with single `-` between them.
+///
+/// A registered check replaces an always available one with the same name.
+///
+/// ```
+/// use format_url::{Constraints, FormatUrl, FormatUrlError, PlaceholderSyntax};
+///
+/// let constraints = Constraints::new()
+///     .with_constraint("sku", |value| value.len() == 8 && value.starts_with("SKU"));
+///
+/// let url = |sku| {
+///     FormatUrl::new("https://shop.example.com")
+///         .with_placeholder_syntax(PlaceholderSyntax::Braces)
+///         .with_path_template("/products/{sku:sku}")
+///         .with_substitutes(vec![("sku", sku)])
+///         .with_constraints(&constraints)
+///         .try_format_url()
+/// };
+/// assert_eq!(url("SKU00042").unwrap(), "https://shop.example.com/products/SKU00042");
+/// assert!(matches!(url("42"), Err(FormatUrlError::ConstraintMismatch { .. })));
+/// ```
+#[derive(Clone, Default)]
+pub struct Constraints {
+    checks: HashMap<String, Check>,
+}
+
+impl Constraints {
+    /// Only the checks that are always available.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a check under `name`, so `{id:name}` only accepts substitutes it returns `true`
+    /// for.
+    pub fn with_constraint(
+        mut self,
+        name: impl Into<String>,
+        check: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.checks.insert(name.into(), Arc::new(check));
+        self
+    }
+
+    /// Whether `value` passes the check called `name`, or `None` when there's no such check.
+    pub fn matches(&self, name: &str, value: &str) -> Option<bool> {
+        match self.checks.get(name) {
+            Some(check) => Some(check(value)),
+            None => builtin(name).map(|check| check(value)),
+        }
+    }
+}
+
+impl fmt::Debug for Constraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.checks.keys()).finish()
+    }
+}
+
+/// The checks that are always available.
+fn builtin(name: &str) -> Option<fn(&str) -> bool> {
+    match name {
+        "integer" => Some(is_integer),
+        "uuid" => Some(is_uuid),
+        "slug" => Some(is_slug),
+        _ => None,
+    }
+}
+
+fn is_integer(value: &str) -> bool {
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn is_uuid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| {
+            group.len() == len && group.bytes().all(|byte| byte.is_ascii_hexdigit())
+        })
+}
+
+fn is_slug(value: &str) -> bool {
+    value.split('-').all(|part| {
+        !part.is_empty()
+            && part
+                .bytes()
+                .all(|byte| byte.is_ascii_lowercase() || byte.is_ascii_digit())
+    })
+}
+
+/// What a placeholder accepts.
+#[derive(Clone, Debug)]
+pub(crate) enum Constraint {
+    /// `{id:name}`, looked up in [`Constraints`].
+    Named(String),
+    /// `:id(pattern)`, a regular expression the whole substitute has to match.
+    #[cfg(feature = "regex")]
+    Pattern {
+        pattern: String,
+        regex: regex::Regex,
+    },
+}
+
+impl Constraint {
+    /// A `:id(pattern)` constraint, or why `pattern` can't be one: it isn't a valid regular
+    /// expression, or the `regex` feature is off.
+    #[cfg(feature = "regex")]
+    pub(crate) fn pattern(pattern: &str) -> Result<Self, String> {
+        let regex =
+            regex::Regex::new(&format!("^(?:{pattern})$")).map_err(|error| error.to_string())?;
+        Ok(Constraint::Pattern {
+            pattern: pattern.to_string(),
+            regex,
+        })
+    }
+
+    #[cfg(not(feature = "regex"))]
+    pub(crate) fn pattern(_pattern: &str) -> Result<Self, String> {
+        Err("pattern constraints need the `regex` feature".to_string())
+    }
+
+    /// Whether `value` passes, or why it can't be checked. Named checks are looked up in
+    /// `constraints` first, if any.
+    pub(crate) fn check(
+        &self,
+        value: &str,
+        constraints: Option<&Constraints>,
+    ) -> Result<bool, String> {
+        match self {
+            Constraint::Named(name) => match constraints {
+                Some(constraints) => constraints.matches(name, value),
+                None => builtin(name).map(|check| check(value)),
+            }
+            .ok_or_else(|| format!("unknown constraint {name}")),
+            #[cfg(feature = "regex")]
+            Constraint::Pattern { regex, .. } => Ok(regex.is_match(value)),
+        }
+    }
+}
+
+impl PartialEq for Constraint {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Constraint::Named(name), Constraint::Named(other)) => name == other,
+            #[cfg(feature = "regex")]
+            (Constraint::Pattern { pattern, .. }, Constraint::Pattern { pattern: other, .. }) => {
+                pattern == other
+            }
+            #[cfg(feature = "regex")]
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Constraint {}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constraint::Named(name) => f.write_str(name),
+            #[cfg(feature = "regex")]
+            Constraint::Pattern { pattern, .. } => write!(f, "({pattern})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constraint::{Constraint, Constraints};
+
+    #[test]
+    fn builtin_test() {
+        let matches = |name, value| Constraints::new().matches(name, value);
+        assert_eq!(matches("integer", "-42"), Some(true));
+        assert_eq!(matches("integer", "4.2"), Some(false));
+        assert_eq!(matches("integer", "-"), Some(false));
+        assert_eq!(
+            matches("uuid", "67e55044-10b1-426f-9247-bb680e5fe0c8"),
+            Some(true)
+        );
+        assert_eq!(
+            matches("uuid", "67e5504410b1426f9247bb680e5fe0c8"),
+            Some(false)
+        );
+        assert_eq!(matches("slug", "format-url-2"), Some(true));
+        assert_eq!(matches("slug", "Format--url"), Some(false));
+        assert_eq!(matches("sku", "x"), None);
+    }
+
+    #[test]
+    fn registered_test() {
+        let constraints = Constraints::new()
+            .with_constraint("hex", |value| {
+                value.bytes().all(|byte| byte.is_ascii_hexdigit())
+            })
+            .with_constraint("integer", |value| value == "one");
+        assert_eq!(constraints.matches("hex", "c0ffee"), Some(true));
+        assert_eq!(constraints.matches("hex", "coffee"), Some(false));
+        assert_eq!(constraints.matches("integer", "1"), Some(false));
+        assert_eq!(
+            Constraint::Named("hex".to_string()).check("c0ffee", None),
+            Err("unknown constraint hex".to_string())
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn pattern_test() {
+        let constraint = Constraint::pattern(r"\d+").unwrap();
+        assert_eq!(constraint.check("42", None), Ok(true));
+        assert_eq!(constraint.check("42a", None), Ok(false));
+        assert!(Constraint::pattern("(").is_err());
+    }
+
+    #[cfg(not(feature = "regex"))]
+    #[test]
+    fn pattern_without_regex_test() {
+        assert_eq!(
+            Constraint::pattern(r"\d+"),
+            Err("pattern constraints need the `regex` feature".to_string())
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Conversions into the URL types of the `url` and `http` crates.
+
+use crate::{FormatUrl, FormatUrlError};
+
+fn rejected(error: impl std::fmt::Display) -> FormatUrlError {
+    FormatUrlError::InvalidUrl {
+        reason: error.to_string(),
+    }
+}
+
+impl FormatUrl<'_> {
+    /// Like [`FormatUrl::try_format_url`], but returns a [`url::Url`]. Unlike
+    /// `format_uri`, this parses the formatted string once more, as the `url` crate
+    /// only builds a `Url` by parsing one. That may also normalize it, such as lowercasing the
+    /// host.
+    #[cfg(feature = "url")]
+    pub fn format_url_as_url(self) -> Result<url::Url, FormatUrlError> {
+        url::Url::parse(&self.try_format_url()?).map_err(rejected)
+    }
+
+    /// Like [`FormatUrl::try_format_url`], but returns an [`http::Uri`]. It's built from the
+    /// scheme and authority of the base and the formatted path and query as they are, without
+    /// parsing the whole URL again. A fragment is left out, HTTP requests don't carry one.
+    #[cfg(feature = "http")]
+    pub fn format_uri(self) -> Result<http::Uri, FormatUrlError> {
+        let url = self.try_format_joined()?;
+
+        let mut parts = http::uri::Parts::default();
+        parts.scheme = url
+            .path
+            .scheme
+            .as_deref()
+            .map(http::uri::Scheme::try_from)
+            .transpose()
+            .map_err(rejected)?;
+        parts.authority = url
+            .path
+            .authority
+            .as_deref()
+            .map(http::uri::Authority::try_from)
+            .transpose()
+            .map_err(rejected)?;
+        let path = match url.path.path.as_ref() {
+            "" => "/",
+            path => path,
+        };
+        let path_and_query = match url.query.as_str() {
+            "" => http::uri::PathAndQuery::try_from(path),
+            query => http::uri::PathAndQuery::try_from(format!("{path}?{query}")),
+        };
+        parts.path_and_query = Some(path_and_query.map_err(rejected)?);
+        http::Uri::from_parts(parts).map_err(rejected)
+    }
+}
+
+#[cfg(feature = "url")]
+impl TryFrom<FormatUrl<'_>> for url::Url {
+    type Error = FormatUrlError;
+
+    fn try_from(format_url: FormatUrl<'_>) -> Result<Self, Self::Error> {
+        format_url.format_url_as_url()
+    }
+}
+
+#[cfg(feature = "http")]
+impl TryFrom<FormatUrl<'_>> for http::Uri {
+    type Error = FormatUrlError;
+
+    fn try_from(format_url: FormatUrl<'_>) -> Result<Self, Self::Error> {
+        format_url.format_uri()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::FormatUrl;
+
+    fn format_url() -> FormatUrl<'static> {
+        FormatUrl::new("https://api.example.com:8443/")
+            .with_path_template("/user/:name")
+            .with_substitutes(vec![("name", "alex tes")])
+            .with_query_params(vec![("active", "true")])
+            .with_fragment("top")
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn url_test() {
+        let url = url::Url::try_from(format_url()).unwrap();
+        assert_eq!(url.host_str(), Some("api.example.com"));
+        assert_eq!(url.port(), Some(8443));
+        assert_eq!(url.path(), "/user/alex%20tes");
+        assert_eq!(url.query(), Some("active=true"));
+        assert_eq!(url.fragment(), Some("top"));
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn uri_test() {
+        use crate::JoinMode;
+
+        let uri = http::Uri::try_from(format_url()).unwrap();
+        assert_eq!(uri.scheme_str(), Some("https"));
+        assert_eq!(uri.host(), Some("api.example.com"));
+        assert_eq!(uri.port_u16(), Some(8443));
+        assert_eq!(uri.path(), "/user/alex%20tes");
+        assert_eq!(uri.query(), Some("active=true"));
+        assert_eq!(
+            FormatUrl::new("https://api.example.com")
+                .format_uri()
+                .map(|uri| uri.to_string()),
+            Ok("https://api.example.com/".to_string())
+        );
+        assert_eq!(
+            FormatUrl::new("https://api.example.com/v1/")
+                .with_path_template("//cdn.example.com/../img")
+                .with_join_mode(JoinMode::Resolve)
+                .with_userinfo("alex", None)
+                .format_uri()
+                .map(|uri| uri.to_string()),
+            Ok("https://cdn.example.com/img".to_string())
+        );
+        assert_eq!(
+            FormatUrl::new("https://api.example.com")
+                .with_userinfo("alex", Some("p:ss"))
+                .format_uri()
+                .map(|uri| uri.authority().map(ToString::to_string)),
+            Ok(Some("alex:p%3Ass@api.example.com".to_string()))
+        );
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn uri_error_test() {
+        use crate::FormatUrlError;
+
+        assert!(matches!(
+            FormatUrl::new("https://api.example.com")
+                .with_query_params(vec![("q", "\"quoted\"")])
+                .disable_encoding()
+                .format_uri(),
+            Err(FormatUrlError::InvalidUrl { .. })
+        ));
+        assert!(matches!(
+            FormatUrl::new("not a url").format_uri(),
+            Err(FormatUrlError::InvalidBase { .. })
+        ));
+    }
+}
@@ -0,0 +1,215 @@
+//! Finding substitutes and query values that are percent-encoded already, and decoding them.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use percent_encoding::{percent_decode_str, AsciiSet};
+
+use crate::encode::write_encoded;
+use crate::query::{QueryParams, QueryValue};
+use crate::{FormatUrlError, SubstituteSource};
+
+/// What to do with substitutes and query values that look percent-encoded already, because they
+/// hold a `%` followed by two hex digits. Encoding those again turns a cursor like `abc%3D%3D`
+/// into `abc%253D%253D`, which the server won't recognize.
+///
+/// Values wrapped in [`PreEncoded`](crate::PreEncoded) are never checked, they're written as is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DoubleEncoding {
+    /// Encode every value, including any `%` in it.
+    #[default]
+    Ignore,
+    /// Encode every value, but report those that look encoded through
+    /// [`FormatUrl::diagnostics`](crate::FormatUrl::diagnostics).
+    Warn,
+    /// Decode values that look encoded before encoding them for the part of the URL they end up
+    /// in. Values that don't decode to UTF-8 are encoded as they are.
+    ///
+    /// ```
+    /// use format_url::{DoubleEncoding, FormatUrl};
+    ///
+    /// let url = FormatUrl::new("https://api.example.com/events")
+    ///     .with_query_params(vec![("cursor", "eyJpZCI6NDJ9%3D%3D"), ("q", "a b")])
+    ///     .with_double_encoding(DoubleEncoding::Normalize)
+    ///     .format_url();
+    /// assert_eq!(url, "https://api.example.com/events?cursor=eyJpZCI6NDJ9==&q=a%20b");
+    /// ```
+    Normalize,
+}
+
+/// Whether `value` holds at least one `%` followed by two hex digits.
+pub(crate) fn looks_encoded(value: &str) -> bool {
+    value
+        .as_bytes()
+        .windows(3)
+        .any(|window| window[0] == b'%' && window[1..].iter().all(u8::is_ascii_hexdigit))
+}
+
+/// `value` percent-decoded when it looks encoded and decodes to UTF-8, otherwise as it is.
+pub(crate) fn normalize(value: &str) -> Cow<'_, str> {
+    if !looks_encoded(value) {
+        return Cow::Borrowed(value);
+    }
+    percent_decode_str(value)
+        .decode_utf8()
+        .unwrap_or(Cow::Borrowed(value))
+}
+
+/// The substitutes of a [`FormatUrl`](crate::FormatUrl), decoded first with
+/// [`DoubleEncoding::Normalize`].
+pub(crate) struct Substitutes<'s> {
+    pub(crate) source: &'s dyn SubstituteSource,
+    pub(crate) double_encoding: DoubleEncoding,
+}
+
+impl Substitutes<'_> {
+    fn normalizes(&self, key: &str) -> bool {
+        self.double_encoding == DoubleEncoding::Normalize && !self.source.is_pre_encoded(key)
+    }
+}
+
+impl SubstituteSource for Substitutes<'_> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        let value = self.source.get(key)?;
+        if !self.normalizes(key) {
+            return Some(value);
+        }
+        Some(match value {
+            Cow::Borrowed(value) => normalize(value),
+            Cow::Owned(value) => Cow::Owned(normalize(&value).into_owned()),
+        })
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        self.source.keys()
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        if !self.normalizes(key) {
+            return self.source.write_substitute(key, out, encode_set);
+        }
+        let value = self.source.get(key)?;
+        Some(write_encoded(out, &normalize(&value), encode_set))
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        self.source.is_pre_encoded(key)
+    }
+}
+
+/// `query_params` with every value that looks encoded decoded.
+pub(crate) fn normalize_query<'a>(query_params: &QueryParams<'a>) -> QueryParams<'a> {
+    query_params
+        .iter()
+        .map(|(key, value)| (key.clone(), normalize_value(value)))
+        .collect()
+}
+
+fn normalize_value<'a>(value: &QueryValue<'a>) -> QueryValue<'a> {
+    match value {
+        QueryValue::Scalar(scalar) => QueryValue::Scalar(match normalize(scalar) {
+            Cow::Borrowed(_) => scalar.clone(),
+            Cow::Owned(decoded) => Cow::Owned(decoded),
+        }),
+        QueryValue::PreEncoded(_) => value.clone(),
+        QueryValue::Seq(values) => QueryValue::Seq(values.iter().map(normalize_value).collect()),
+        QueryValue::Map(entries) => QueryValue::Map(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), normalize_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+/// An [`FormatUrlError::PercentEncodedValue`] for every substitute and query value that looks
+/// encoded, substitutes first.
+pub(crate) fn encoded_values(
+    substitutes: &dyn SubstituteSource,
+    query_params: Option<&QueryParams>,
+) -> Vec<FormatUrlError> {
+    let mut found = Vec::new();
+    for key in substitutes.keys() {
+        if substitutes.is_pre_encoded(&key) {
+            continue;
+        }
+        if let Some(value) = substitutes.get(&key).filter(|value| looks_encoded(value)) {
+            found.push(FormatUrlError::PercentEncodedValue {
+                key: key.to_string(),
+                value: value.into_owned(),
+            });
+        }
+    }
+    for (key, value) in query_params.into_iter().flatten() {
+        push_encoded_scalars(key, value, &mut found);
+    }
+    found
+}
+
+/// Nested values are reported under the key of the parameter holding them.
+fn push_encoded_scalars(key: &str, value: &QueryValue, found: &mut Vec<FormatUrlError>) {
+    match value {
+        QueryValue::Scalar(scalar) if looks_encoded(scalar) => {
+            found.push(FormatUrlError::PercentEncodedValue {
+                key: key.to_string(),
+                value: scalar.to_string(),
+            })
+        }
+        QueryValue::Scalar(_) | QueryValue::PreEncoded(_) => {}
+        QueryValue::Seq(values) => values
+            .iter()
+            .for_each(|value| push_encoded_scalars(key, value, found)),
+        QueryValue::Map(entries) => entries
+            .iter()
+            .for_each(|(_, value)| push_encoded_scalars(key, value, found)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::double_encoding::{encoded_values, looks_encoded, normalize, Substitutes};
+    use crate::{DoubleEncoding, PreEncoded, SubstituteSource};
+
+    #[test]
+    fn looks_encoded_test() {
+        assert!(looks_encoded("a%20b"));
+        assert!(looks_encoded("%2f"));
+        assert!(!looks_encoded("100%"));
+        assert!(!looks_encoded("50%off"));
+        assert!(!looks_encoded("%2"));
+        assert!(!looks_encoded("a b"));
+    }
+
+    #[test]
+    fn normalize_test() {
+        assert_eq!(normalize("a%20b%2Fc"), "a b/c");
+        assert!(matches!(normalize("plain"), Cow::Borrowed("plain")));
+        // Not UTF-8 once decoded, so it's left alone.
+        assert_eq!(normalize("%FF%FE"), "%FF%FE");
+    }
+
+    #[test]
+    fn substitutes_test() {
+        let pairs = [("a", PreEncoded("x%2Fy")), ("b", PreEncoded("1"))];
+        let substitutes = Substitutes {
+            source: &pairs,
+            double_encoding: DoubleEncoding::Normalize,
+        };
+        assert_eq!(substitutes.get("a").as_deref(), Some("x%2Fy"));
+
+        let pairs = [("a", "x%2Fy")];
+        let substitutes = Substitutes {
+            source: &pairs,
+            double_encoding: DoubleEncoding::Normalize,
+        };
+        assert_eq!(substitutes.get("a").as_deref(), Some("x/y"));
+        assert_eq!(encoded_values(&pairs, None).len(), 1);
+    }
+}
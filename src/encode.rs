@@ -0,0 +1,461 @@
+//! Percent-encoding while writing, and what to encode in each part of the URL.
+
+use std::fmt::{self, Display, Write};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Everything but the unreserved characters `ALPHA / DIGIT / "-" / "." / "_" / "~"`.
+pub(crate) const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Everything but the unreserved characters and the sub-delims `! $ & ' ( ) * + , ; =`.
+const USERINFO: &AsciiSet = &UNRESERVED
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=');
+
+/// `pchar` without `:`.
+const PATH_SEGMENT: &AsciiSet = &USERINFO.remove(b'@');
+
+/// `pchar`, `/` and `?`.
+const FRAGMENT: &AsciiSet = &PATH_SEGMENT.remove(b':').remove(b'/').remove(b'?');
+
+/// What's allowed in a fragment, except for `&` and `=` which separate the pairs and `+`, which
+/// form decoding takes for a space.
+const QUERY_KEY: &AsciiSet = &FRAGMENT.add(b'&').add(b'=').add(b'+');
+
+/// Like a key, but `=` can stay, only the first one separates the key from the value.
+const QUERY_VALUE: &AsciiSet = &QUERY_KEY.remove(b'=');
+
+/// A part of the URL with its own rules for which characters have to be percent-encoded.
+///
+/// The defaults follow [RFC 3986](https://www.rfc-editor.org/rfc/rfc3986#section-2): unreserved
+/// characters like `-`, `.`, `_` and `~` are never encoded, and characters with a meaning of their
+/// own in a component, like `/` in a path segment or `&` in a query value, always are. Override
+/// them with [`FormatUrl::with_encode_set`](crate::FormatUrl::with_encode_set).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Component {
+    /// A substitute in the path template. On top of `/`, `?` and `#`, this encodes `:` so a
+    /// substitute can't turn a relative path into a scheme.
+    PathSegment,
+    /// A query parameter key, or a key within a nested one.
+    QueryKey,
+    /// A query parameter value.
+    QueryValue,
+    /// A substitute in the fragment template.
+    Fragment,
+    /// The user name and password given to
+    /// [`FormatUrl::with_userinfo`](crate::FormatUrl::with_userinfo).
+    Userinfo,
+}
+
+impl Component {
+    /// The characters that are percent-encoded in this component unless overridden.
+    ///
+    /// ```
+    /// use format_url::{AsciiSet, Component};
+    ///
+    /// // Also encode `'` in query values.
+    /// const QUERY_VALUE: &AsciiSet = &Component::QueryValue.default_encode_set().add(b'\'');
+    /// ```
+    pub const fn default_encode_set(self) -> &'static AsciiSet {
+        match self {
+            Component::PathSegment => PATH_SEGMENT,
+            Component::QueryKey => QUERY_KEY,
+            Component::QueryValue => QUERY_VALUE,
+            Component::Fragment => FRAGMENT,
+            Component::Userinfo => USERINFO,
+        }
+    }
+}
+
+/// Text that's already percent-encoded, which goes into the URL as is. Use it for values such as
+/// pagination cursors that an API hands out encoded, while everything else is still encoded.
+///
+/// ```
+/// use format_url::{FormatUrl, PreEncoded};
+///
+/// let url = FormatUrl::new("https://api.example.com")
+///     .with_path_template("/users/:id/events")
+///     .with_substitutes(vec![("id", "a b")])
+///     .with_query_param("cursor", PreEncoded("eyJpZCI6MX0%3D"))
+///     .with_query_param("q", "a+b")
+///     .format_url();
+/// assert_eq!(
+///     url,
+///     "https://api.example.com/users/a%20b/events?cursor=eyJpZCI6MX0%3D&q=a%2Bb"
+/// );
+/// ```
+///
+/// With the `serde` feature, `PreEncoded` fields are kept as they are by
+/// `FormatUrl::with_query` and `FormatUrl::with_serialized_substitutes` too.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PreEncoded<T>(pub T);
+
+/// A value for a placeholder or query parameter: anything that implements `Display`, which is
+/// percent-encoded for the part of the URL it ends up in, or [`PreEncoded`] text, which isn't.
+pub trait UrlValue {
+    /// Write the text of the value, before any percent-encoding.
+    fn fmt_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+    /// Whether the text is already percent-encoded and should be written as is.
+    fn is_pre_encoded(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Display + ?Sized> UrlValue for T {
+    fn fmt_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt(f)
+    }
+}
+
+impl<T: Display> UrlValue for PreEncoded<T> {
+    fn fmt_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+
+    fn is_pre_encoded(&self) -> bool {
+        true
+    }
+}
+
+/// Lets values of different types share one list of substitutes, as in
+/// `vec![("id", &7 as &dyn UrlValue), ("cursor", &PreEncoded("a%2Bb"))]`.
+impl UrlValue for &(dyn UrlValue + '_) {
+    fn fmt_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt_text(f)
+    }
+
+    fn is_pre_encoded(&self) -> bool {
+        (**self).is_pre_encoded()
+    }
+}
+
+/// Displays the text of a [`UrlValue`].
+pub(crate) struct Text<'v, V: ?Sized>(pub(crate) &'v V);
+
+impl<V: UrlValue + ?Sized> Display for Text<'_, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_text(f)
+    }
+}
+
+/// Write `value` percent-encoded with `encode_set`, or as is without one.
+pub(crate) fn write_encoded<W: fmt::Write + ?Sized>(
+    out: &mut W,
+    value: &str,
+    encode_set: Option<&'static AsciiSet>,
+) -> fmt::Result {
+    match encode_set {
+        Some(encode_set) => PercentEncode::new(out, encode_set).write_str(value),
+        None => out.write_str(value),
+    }
+}
+
+/// Write the text of `value` percent-encoded with `encode_set`, or as is when there's no set or
+/// the value is pre-encoded.
+pub(crate) fn write_value<W, V>(
+    out: &mut W,
+    value: &V,
+    encode_set: Option<&'static AsciiSet>,
+) -> fmt::Result
+where
+    W: fmt::Write + ?Sized,
+    V: UrlValue + ?Sized,
+{
+    match encode_set.filter(|_| !value.is_pre_encoded()) {
+        Some(encode_set) => write!(PercentEncode::new(out, encode_set), "{}", Text(value)),
+        None => write!(out, "{}", Text(value)),
+    }
+}
+
+/// The encode set in use for each [`Component`], or none when its encoding is disabled.
+#[derive(Clone, Copy)]
+pub(crate) struct EncodeSets {
+    path_segment: Option<&'static AsciiSet>,
+    query_key: Option<&'static AsciiSet>,
+    query_value: Option<&'static AsciiSet>,
+    fragment: Option<&'static AsciiSet>,
+    userinfo: Option<&'static AsciiSet>,
+}
+
+impl EncodeSets {
+    pub(crate) fn get(&self, component: Component) -> Option<&'static AsciiSet> {
+        match component {
+            Component::PathSegment => self.path_segment,
+            Component::QueryKey => self.query_key,
+            Component::QueryValue => self.query_value,
+            Component::Fragment => self.fragment,
+            Component::Userinfo => self.userinfo,
+        }
+    }
+
+    pub(crate) fn set(&mut self, component: Component, encode_set: Option<&'static AsciiSet>) {
+        match component {
+            Component::PathSegment => self.path_segment = encode_set,
+            Component::QueryKey => self.query_key = encode_set,
+            Component::QueryValue => self.query_value = encode_set,
+            Component::Fragment => self.fragment = encode_set,
+            Component::Userinfo => self.userinfo = encode_set,
+        }
+    }
+}
+
+impl Default for EncodeSets {
+    fn default() -> Self {
+        Self {
+            path_segment: Some(PATH_SEGMENT),
+            query_key: Some(QUERY_KEY),
+            query_value: Some(QUERY_VALUE),
+            fragment: Some(FRAGMENT),
+            userinfo: Some(USERINFO),
+        }
+    }
+}
+
+/// A writer that percent-encodes everything written to it before passing it on, so values can be
+/// encoded straight from their `Display` implementation.
+pub(crate) struct PercentEncode<'w, W: ?Sized> {
+    out: &'w mut W,
+    encode_set: &'static AsciiSet,
+}
+
+impl<'w, W: fmt::Write + ?Sized> PercentEncode<'w, W> {
+    pub(crate) fn new(out: &'w mut W, encode_set: &'static AsciiSet) -> Self {
+        Self { out, encode_set }
+    }
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for PercentEncode<'_, W> {
+    fn write_str(&mut self, value: &str) -> fmt::Result {
+        utf8_percent_encode(value, self.encode_set).try_for_each(|chunk| self.out.write_str(chunk))
+    }
+}
+
+/// A writer that drops up to `limit` leading `c`s before passing the rest on.
+pub(crate) struct TrimStart<'w, W: ?Sized> {
+    out: &'w mut W,
+    c: char,
+    limit: usize,
+}
+
+impl<'w, W: fmt::Write + ?Sized> TrimStart<'w, W> {
+    pub(crate) fn new(out: &'w mut W, c: char, limit: usize) -> Self {
+        Self { out, c, limit }
+    }
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for TrimStart<'_, W> {
+    fn write_str(&mut self, mut value: &str) -> fmt::Result {
+        while self.limit > 0 {
+            match value.strip_prefix(self.c) {
+                Some(rest) => {
+                    value = rest;
+                    self.limit -= 1;
+                }
+                None if value.is_empty() => return Ok(()),
+                None => self.limit = 0,
+            }
+        }
+        self.out.write_str(value)
+    }
+}
+
+/// A writer for a path appended with [`JoinMode::PrefixAppend`](crate::JoinMode::PrefixAppend)
+/// onto a base without its trailing `/`s. It writes a single `/` before the path and drops the
+/// `/`s the path starts with, or, when the path is empty, puts the trimmed `/`s back.
+pub(crate) struct AppendPath<'w, W: ?Sized> {
+    out: &'w mut W,
+    trimmed: &'w str,
+    started: bool,
+    trimming: bool,
+}
+
+impl<'w, W: fmt::Write + ?Sized> AppendPath<'w, W> {
+    pub(crate) fn new(out: &'w mut W, trimmed: &'w str) -> Self {
+        Self {
+            out,
+            trimmed,
+            started: false,
+            trimming: true,
+        }
+    }
+
+    /// Put the trimmed `/`s back if no path was written.
+    pub(crate) fn finish(self) -> fmt::Result {
+        match self.started {
+            true => Ok(()),
+            false => self.out.write_str(self.trimmed),
+        }
+    }
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for AppendPath<'_, W> {
+    fn write_str(&mut self, mut value: &str) -> fmt::Result {
+        if value.is_empty() {
+            return Ok(());
+        }
+        if !self.started {
+            self.started = true;
+            self.out.write_char('/')?;
+        }
+        if self.trimming {
+            value = value.trim_start_matches('/');
+            if value.is_empty() {
+                return Ok(());
+            }
+            self.trimming = false;
+        }
+        self.out.write_str(value)
+    }
+}
+
+/// A writer that holds back everything written to it while that's only `.` or `..`, so a value
+/// that would make a dot segment can be written as `%2E` or `%2E%2E` instead.
+pub(crate) struct EscapeDots<'w, W: ?Sized> {
+    out: &'w mut W,
+    dots: usize,
+    passed: bool,
+}
+
+impl<'w, W: fmt::Write + ?Sized> EscapeDots<'w, W> {
+    pub(crate) fn new(out: &'w mut W) -> Self {
+        Self {
+            out,
+            dots: 0,
+            passed: false,
+        }
+    }
+
+    /// Write the dots still held back, percent-encoded.
+    pub(crate) fn finish(self) -> fmt::Result {
+        (0..self.dots).try_for_each(|_| self.out.write_str("%2E"))
+    }
+}
+
+impl<W: fmt::Write + ?Sized> fmt::Write for EscapeDots<'_, W> {
+    fn write_str(&mut self, value: &str) -> fmt::Result {
+        if !self.passed {
+            if value.bytes().all(|byte| byte == b'.') && self.dots + value.len() <= 2 {
+                self.dots += value.len();
+                return Ok(());
+            }
+            self.passed = true;
+            self.out.write_str(&".."[..self.dots])?;
+            self.dots = 0;
+        }
+        self.out.write_str(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use percent_encoding::NON_ALPHANUMERIC;
+
+    use crate::encode::{AppendPath, EscapeDots, PercentEncode, TrimStart};
+    use crate::Component;
+
+    #[test]
+    fn percent_encode_test() {
+        let mut out = String::from("/");
+        let value = "a/b";
+        write!(
+            PercentEncode::new(&mut out, NON_ALPHANUMERIC),
+            "{value} {}",
+            1
+        )
+        .unwrap();
+        assert_eq!(out, "/a%2Fb%201");
+    }
+
+    #[test]
+    fn trim_start_test() {
+        let mut out = String::new();
+        let mut trimmed = TrimStart::new(&mut out, '/', 2);
+        for chunk in ["", "/", "//a/"] {
+            trimmed.write_str(chunk).unwrap();
+        }
+        assert_eq!(out, "/a/");
+    }
+
+    #[test]
+    fn append_path_test() {
+        let append = |chunks: &[&str]| {
+            let mut out = String::from("/api");
+            let mut appended = AppendPath::new(&mut out, "//");
+            for chunk in chunks {
+                appended.write_str(chunk).unwrap();
+            }
+            appended.finish().unwrap();
+            out
+        };
+        assert_eq!(append(&["", "/", "//a/", "/b"]), "/api/a//b");
+        assert_eq!(append(&["/"]), "/api/");
+        assert_eq!(append(&[""]), "/api//");
+    }
+
+    #[test]
+    fn escape_dots_test() {
+        let escape = |chunks: &[&str]| {
+            let mut out = String::new();
+            let mut escaped = EscapeDots::new(&mut out);
+            for chunk in chunks {
+                escaped.write_str(chunk).unwrap();
+            }
+            escaped.finish().unwrap();
+            out
+        };
+        assert_eq!(escape(&["."]), "%2E");
+        assert_eq!(escape(&[".", "."]), "%2E%2E");
+        assert_eq!(escape(&["..", "."]), "...");
+        assert_eq!(escape(&[".", ".a"]), "..a");
+        assert_eq!(escape(&["", ".x"]), ".x");
+        assert_eq!(escape(&[]), "");
+    }
+
+    #[test]
+    fn default_encode_sets_test() {
+        let encode = |component: Component| {
+            let mut out = String::new();
+            PercentEncode::new(&mut out, component.default_encode_set())
+                .write_str("a-._~ /?#[]@:!$&'()*+,;=%\"é")
+                .unwrap();
+            out
+        };
+        assert_eq!(
+            encode(Component::PathSegment),
+            "a-._~%20%2F%3F%23%5B%5D@%3A!$&'()*+,;=%25%22%C3%A9"
+        );
+        assert_eq!(
+            encode(Component::QueryKey),
+            "a-._~%20/?%23%5B%5D@:!$%26'()*%2B,;%3D%25%22%C3%A9"
+        );
+        assert_eq!(
+            encode(Component::QueryValue),
+            "a-._~%20/?%23%5B%5D@:!$%26'()*%2B,;=%25%22%C3%A9"
+        );
+        assert_eq!(
+            encode(Component::Fragment),
+            "a-._~%20/?%23%5B%5D@:!$&'()*+,;=%25%22%C3%A9"
+        );
+        assert_eq!(
+            encode(Component::Userinfo),
+            "a-._~%20%2F%3F%23%5B%5D%40%3A!$&'()*+,;=%25%22%C3%A9"
+        );
+    }
+}
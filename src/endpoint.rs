@@ -0,0 +1,84 @@
+//! Typed endpoints.
+
+use crate::FormatUrl;
+
+/// A request whose URL is built from its own fields. Usually derived, which needs the `macros`
+/// feature.
+///
+/// The derive takes the path template from `#[endpoint(path = "...")]` on the struct, fills its
+/// `:name` placeholders from the fields marked `#[endpoint(path)]` and adds the fields marked
+/// `#[endpoint(query)]` as query parameters. Add `rename = "..."` when the field and parameter
+/// names differ. Values are stringified with `Display`, `None` query fields are left out and `Vec`
+/// fields are written according to the [`ArrayFormat`](crate::ArrayFormat). Every placeholder
+/// must have a path field and the other way around, which is checked at compile time. The path
+/// field of a placeholder the path can leave out, because it's in a `(/...)` group, marked `?` or
+/// has a `=default`, may be an `Option`, which leaves it out when `None`.
+///
+/// The field attributes are `#[endpoint(path)]` and `#[endpoint(query)]` rather than a bare
+/// `#[path]` and `#[query]` because `#[path]` is a built-in attribute, the one that sets the file
+/// of a module, which a derive can't take over. Keeping both under `endpoint` keeps them alike.
+///
+/// ```
+/// # #[cfg(feature = "macros")]
+/// # {
+/// use format_url::Endpoint;
+///
+/// #[derive(Endpoint)]
+/// #[endpoint(path = "/repos/:owner/:repo/issues")]
+/// struct ListIssues {
+///     #[endpoint(path)]
+///     owner: String,
+///     #[endpoint(path)]
+///     repo: String,
+///     #[endpoint(query)]
+///     state: Option<&'static str>,
+///     #[endpoint(query, rename = "per_page")]
+///     page_size: u32,
+/// }
+///
+/// let request = ListIssues {
+///     owner: "alextes".to_string(),
+///     repo: "format-url".to_string(),
+///     state: None,
+///     page_size: 50,
+/// };
+/// assert_eq!(
+///     request.format_url("https://api.github.com"),
+///     "https://api.github.com/repos/alextes/format-url/issues?per_page=50"
+/// );
+/// # }
+/// ```
+#[cfg_attr(
+    feature = "macros",
+    doc = r#"
+A placeholder without a matching field doesn't compile.
+
+```compile_fail
+#[derive(format_url::Endpoint)]
+#[endpoint(path = "/repos/:owner/:repo")]
+struct GetRepo {
+    #[endpoint(path)]
+    owner: String,
+}
+```
+
+Neither does an `Option` field for a placeholder that's always in the path.
+
+```compile_fail
+#[derive(format_url::Endpoint)]
+#[endpoint(path = "/repos/:owner")]
+struct GetOwner {
+    #[endpoint(path)]
+    owner: Option<String>,
+}
+```"#
+)]
+pub trait Endpoint {
+    /// A [`FormatUrl`] for this endpoint on `base`, to adjust further before formatting.
+    fn to_format_url<'a>(&'a self, base: &'a str) -> FormatUrl<'a>;
+
+    /// The URL of this endpoint on `base`.
+    fn format_url(&self, base: &str) -> String {
+        self.to_format_url(base).format_url()
+    }
+}
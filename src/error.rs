@@ -0,0 +1,102 @@
+//! Errors returned by fallible formatting.
+
+use std::fmt;
+
+/// Everything that can go wrong while turning a [`FormatUrl`](crate::FormatUrl) into a URL.
+///
+/// Positions are byte offsets into the template or base URL, except for substitutes, where the
+/// position is the index of the pair in the list of substitutes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FormatUrlError {
+    /// A placeholder in the path template has no matching substitute.
+    MissingSubstitute { key: String, position: usize },
+    /// A substitute does not match any placeholder in the path template.
+    UnusedSubstitute { key: String, position: usize },
+    /// The path template could not be parsed.
+    MalformedTemplate { reason: String, position: usize },
+    /// The base URL is not usable as the start of a URL.
+    InvalidBase { reason: String, position: usize },
+    /// A substitute is empty, which would leave an empty segment in the path.
+    EmptySegment { key: String, position: usize },
+    /// The substitute for a splat placeholder has a `..` segment, or the substitute for another
+    /// placeholder is `.` or `..`, see
+    /// [`FormatUrl::allow_dot_segments`](crate::FormatUrl::allow_dot_segments).
+    DotSegment { key: String, position: usize },
+    /// A substitute doesn't pass the constraint its placeholder puts on it, such as `{id:uuid}`.
+    ConstraintMismatch {
+        key: String,
+        constraint: String,
+        position: usize,
+    },
+    /// A value passed to `with_query` could not be turned into query parameters.
+    InvalidQuery { key: String, reason: String },
+    /// A value passed to `with_serialized_substitutes` could not be turned into substitutes.
+    InvalidSubstitute { key: String, reason: String },
+    /// An added query parameter is already in the base URL, see
+    /// [`DuplicateKeys::Error`](crate::DuplicateKeys::Error).
+    DuplicateQueryKey { key: String },
+    /// The `url` or `http` crate rejected the formatted URL.
+    InvalidUrl { reason: String },
+    /// A substitute or query value looks percent-encoded already, see
+    /// [`DoubleEncoding::Warn`](crate::DoubleEncoding::Warn).
+    PercentEncodedValue { key: String, value: String },
+}
+
+impl fmt::Display for FormatUrlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatUrlError::MissingSubstitute { key, position } => write!(
+                f,
+                "placeholder {key} at position {position} has no substitute"
+            ),
+            FormatUrlError::UnusedSubstitute { key, position } => write!(
+                f,
+                "substitute {key} at index {position} does not match any placeholder"
+            ),
+            FormatUrlError::MalformedTemplate { reason, position } => {
+                write!(f, "malformed template at position {position}: {reason}")
+            }
+            FormatUrlError::InvalidBase { reason, position } => {
+                write!(f, "invalid base URL at position {position}: {reason}")
+            }
+            FormatUrlError::EmptySegment { key, position } => write!(
+                f,
+                "substitute for placeholder {key} at position {position} is empty"
+            ),
+            FormatUrlError::DotSegment { key, position } => write!(
+                f,
+                "substitute for placeholder {key} at position {position} makes a dot segment"
+            ),
+            FormatUrlError::ConstraintMismatch {
+                key,
+                constraint,
+                position,
+            } => write!(
+                f,
+                "substitute for placeholder {key} at position {position} doesn't match {constraint}"
+            ),
+            FormatUrlError::InvalidQuery { key, reason } if key.is_empty() => {
+                write!(f, "invalid query: {reason}")
+            }
+            FormatUrlError::InvalidQuery { key, reason } => {
+                write!(f, "invalid query parameter {key}: {reason}")
+            }
+            FormatUrlError::InvalidSubstitute { key, reason } if key.is_empty() => {
+                write!(f, "invalid substitutes: {reason}")
+            }
+            FormatUrlError::InvalidSubstitute { key, reason } => {
+                write!(f, "invalid substitute {key}: {reason}")
+            }
+            FormatUrlError::DuplicateQueryKey { key } => {
+                write!(f, "query parameter {key} is already in the base URL")
+            }
+            FormatUrlError::InvalidUrl { reason } => write!(f, "invalid URL: {reason}"),
+            FormatUrlError::PercentEncodedValue { key, value } => {
+                write!(f, "value {value:?} for {key} looks percent-encoded already")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FormatUrlError {}
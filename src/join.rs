@@ -0,0 +1,272 @@
+//! Joining the base URL and the formatted path.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::BaseUrl;
+
+/// How the formatted path is joined onto the base URL, shown for the base `https://x.com/api/v1`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JoinMode {
+    /// Append the path as is, dropping its leading `/` when the base ends with one.
+    /// `user` gives `https://x.com/api/v1user`.
+    #[default]
+    Concat,
+    /// Append the path with exactly one `/` between it and the base.
+    /// `user` and `/user` both give `https://x.com/api/v1/user`.
+    PrefixAppend,
+    /// Treat the path as a reference relative to the base and resolve it following
+    /// [RFC 3986 section 5.2](https://www.rfc-editor.org/rfc/rfc3986#section-5.2), including the
+    /// removal of `.` and `..` segments. `user` gives `https://x.com/api/user`, `/user` gives
+    /// `https://x.com/user` and `./v2/user` gives `https://x.com/api/v2/user`.
+    ///
+    /// As the RFC prescribes, a query or fragment in the base is only kept when the path is empty.
+    Resolve,
+}
+
+/// Remove `.` and `..` segments as described in RFC 3986 section 5.2.4.
+pub(crate) fn remove_dot_segments(path: &str) -> String {
+    let mut input = path;
+    let mut output = String::with_capacity(path.len());
+
+    while !input.is_empty() {
+        if let Some(rest) = input
+            .strip_prefix("../")
+            .or_else(|| input.strip_prefix("./"))
+        {
+            input = rest;
+        } else if input.starts_with("/./") {
+            input = &input[2..];
+        } else if input == "/." {
+            input = "/";
+        } else if input.starts_with("/../") || input == "/.." {
+            input = if input == "/.." { "/" } else { &input[3..] };
+            output.truncate(output.rfind('/').unwrap_or(0));
+        } else if input == "." || input == ".." {
+            input = "";
+        } else {
+            let segment_end = input
+                .bytes()
+                .skip(1)
+                .position(|byte| byte == b'/')
+                .map_or(input.len(), |index| index + 1);
+            output.push_str(&input[..segment_end]);
+            input = &input[segment_end..];
+        }
+    }
+
+    output
+}
+
+/// The scheme, authority and path of the base URL with the formatted path joined onto it.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct JoinedPath<'a> {
+    pub(crate) scheme: Option<Cow<'a, str>>,
+    pub(crate) authority: Option<Cow<'a, str>>,
+    pub(crate) path: Cow<'a, str>,
+}
+
+impl fmt::Display for JoinedPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(scheme) = &self.scheme {
+            write!(f, "{scheme}:")?;
+        }
+        if let Some(authority) = &self.authority {
+            write!(f, "//{authority}")?;
+        }
+        f.write_str(&self.path)
+    }
+}
+
+/// A formatted URL, kept in its parts until it's written.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct JoinedUrl<'a> {
+    pub(crate) path: JoinedPath<'a>,
+    /// The query without the `?`, empty when there is none.
+    pub(crate) query: String,
+    /// The fragment without the `#`.
+    pub(crate) fragment: Option<String>,
+}
+
+impl fmt::Display for JoinedUrl<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path)?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query)?;
+        }
+        match &self.fragment {
+            Some(fragment) => write!(f, "#{fragment}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Resolve `reference`, without query or fragment, against `base` with `authority` in place of
+/// its own.
+fn resolve<'a>(
+    base: &BaseUrl<'a>,
+    authority: Option<Cow<'a, str>>,
+    reference: &str,
+) -> JoinedPath<'a> {
+    let reference = BaseUrl::split(reference);
+    let path = reference.path();
+    let owned = |part: Option<&str>| part.map(|part| Cow::Owned(part.to_string()));
+    let joined = |scheme, authority, path: String| JoinedPath {
+        scheme,
+        authority,
+        path: Cow::Owned(path),
+    };
+    let scheme = base.scheme().map(Cow::Borrowed);
+
+    match (reference.scheme(), reference.authority()) {
+        (Some(_), _) => joined(
+            owned(reference.scheme()),
+            owned(reference.authority()),
+            remove_dot_segments(path),
+        ),
+        (None, Some(_)) => joined(
+            scheme,
+            owned(reference.authority()),
+            remove_dot_segments(path),
+        ),
+        (None, None) if path.is_empty() => JoinedPath {
+            scheme,
+            authority,
+            path: Cow::Borrowed(base.path()),
+        },
+        (None, None) if path.starts_with('/') => {
+            joined(scheme, authority, remove_dot_segments(path))
+        }
+        (None, None) => {
+            let merged = if authority.is_some() && base.path().is_empty() {
+                format!("/{path}")
+            } else {
+                let directory_end = base.path().rfind('/').map_or(0, |index| index + 1);
+                format!("{}{path}", &base.path()[..directory_end])
+            };
+            joined(scheme, authority, remove_dot_segments(&merged))
+        }
+    }
+}
+
+/// Join the formatted path, without query or fragment, onto `base` with `authority` in place of
+/// its own.
+pub(crate) fn join_path<'a>(
+    base: &BaseUrl<'a>,
+    authority: Option<Cow<'a, str>>,
+    path: &str,
+    join_mode: JoinMode,
+) -> JoinedPath<'a> {
+    let base_path = base.path();
+    let path = match join_mode {
+        JoinMode::Concat if base_path.ends_with('/') && path.starts_with('/') => {
+            Cow::Owned(format!("{base_path}{}", &path[1..]))
+        }
+        JoinMode::Concat => Cow::Owned(format!("{base_path}{path}")),
+        JoinMode::PrefixAppend if path.is_empty() => Cow::Borrowed(base_path),
+        JoinMode::PrefixAppend => Cow::Owned(format!(
+            "{}/{}",
+            base_path.trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )),
+        JoinMode::Resolve => return resolve(base, authority, path),
+    };
+    JoinedPath {
+        scheme: base.scheme().map(Cow::Borrowed),
+        authority,
+        path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::join::{join_path, remove_dot_segments, JoinMode};
+    use crate::BaseUrl;
+
+    #[test]
+    fn remove_dot_segments_test() {
+        let cases = [
+            ("/a/b/c/./../../g", "/a/g"),
+            ("mid/content=5/../6", "mid/6"),
+            ("/a/..", "/"),
+            ("/a/.", "/a/"),
+            ("../../g", "g"),
+            ("/./g", "/g"),
+            ("/g..", "/g.."),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(remove_dot_segments(path), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn join_modes_test() {
+        let cases = [
+            (
+                "https://x.com/api/v1",
+                "user",
+                JoinMode::Concat,
+                "https://x.com/api/v1user",
+            ),
+            (
+                "https://x.com/api/v1/",
+                "/user",
+                JoinMode::Concat,
+                "https://x.com/api/v1/user",
+            ),
+            (
+                "https://x.com/api/v1",
+                "user",
+                JoinMode::PrefixAppend,
+                "https://x.com/api/v1/user",
+            ),
+            (
+                "https://x.com/api/v1//",
+                "//user",
+                JoinMode::PrefixAppend,
+                "https://x.com/api/v1/user",
+            ),
+            (
+                "https://x.com/api/v1",
+                "",
+                JoinMode::PrefixAppend,
+                "https://x.com/api/v1",
+            ),
+            (
+                "https://x.com/api/v1",
+                "user",
+                JoinMode::Resolve,
+                "https://x.com/api/user",
+            ),
+            (
+                "https://x.com/api/v1/",
+                "user",
+                JoinMode::Resolve,
+                "https://x.com/api/v1/user",
+            ),
+            (
+                "https://x.com",
+                "user",
+                JoinMode::Resolve,
+                "https://x.com/user",
+            ),
+            (
+                "https://x.com/api/v1/",
+                "../v2/./user",
+                JoinMode::Resolve,
+                "https://x.com/api/v2/user",
+            ),
+        ];
+        for (base, path, join_mode, expected) in cases {
+            let base_url = BaseUrl::split(base);
+            let authority = base_url.authority().map(Cow::Borrowed);
+            assert_eq!(
+                join_path(&base_url, authority, path, join_mode).to_string(),
+                expected,
+                "{base} {path} {join_mode:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,544 @@
+//! Query parameters, which may hold nested maps and lists, and how they're written out.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+use percent_encoding::{percent_decode_str, AsciiSet, NON_ALPHANUMERIC};
+
+use crate::encode::{write_encoded, PercentEncode, Text};
+use crate::{FormatUrlError, UrlValue};
+
+/// A query parameter value. Nested values are written using bracket notation, as in
+/// `filter[status]=open&ids[]=1&ids[]=2`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+pub(crate) enum QueryValue<'a> {
+    Scalar(Cow<'a, str>),
+    /// A scalar that's already percent-encoded.
+    PreEncoded(Cow<'a, str>),
+    Seq(Vec<QueryValue<'a>>),
+    Map(Vec<(Cow<'a, str>, QueryValue<'a>)>),
+}
+
+impl QueryValue<'_> {
+    /// A scalar holding the text of `value`.
+    pub(crate) fn from_value<V: UrlValue + ?Sized>(value: &V) -> QueryValue<'static> {
+        let text = Cow::Owned(Text(value).to_string());
+        if value.is_pre_encoded() {
+            QueryValue::PreEncoded(text)
+        } else {
+            QueryValue::Scalar(text)
+        }
+    }
+
+    fn is_scalar(&self) -> bool {
+        matches!(self, QueryValue::Scalar(_) | QueryValue::PreEncoded(_))
+    }
+}
+
+/// How sequences in the query are written, shown for `ids: vec![1, 2]`.
+///
+/// Formats that match an OpenAPI query parameter style say which one.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArrayFormat {
+    /// `ids[]=1&ids[]=2`
+    #[default]
+    Brackets,
+    /// `ids[0]=1&ids[1]=2`
+    Indices,
+    /// `ids=1&ids=2`, OpenAPI's `form` style with `explode: true`.
+    Repeat,
+    /// `ids=1,2`, OpenAPI's `form` style with `explode: false`.
+    Comma,
+    /// `ids=1%202`, OpenAPI's `spaceDelimited` style with `explode: false`.
+    ///
+    /// A space in an element is written as `%20` too, so `["1", "2 3"]` and `["1", "2", "3"]`
+    /// come out the same. [`FormatUrl::try_format_url`](crate::FormatUrl::try_format_url) fails
+    /// on such elements with [`FormatUrlError::InvalidQuery`].
+    Space,
+    /// `ids=1|2`, OpenAPI's `pipeDelimited` style with `explode: false`.
+    Pipe,
+}
+
+impl ArrayFormat {
+    /// The delimiter joining all elements into a single value, if this format does that.
+    fn delimiter(self) -> Option<&'static str> {
+        match self {
+            ArrayFormat::Comma => Some(","),
+            ArrayFormat::Space => Some("%20"),
+            ArrayFormat::Pipe => Some("|"),
+            ArrayFormat::Brackets | ArrayFormat::Indices | ArrayFormat::Repeat => None,
+        }
+    }
+}
+
+/// An [`FormatUrlError::InvalidQuery`] for the first list element holding the character
+/// `array_format` separates elements with, when it can't be told apart from that separator: always
+/// for [`ArrayFormat::Space`], and for the other delimited formats when values aren't encoded.
+pub(crate) fn ambiguous_element(
+    query_params: &QueryParams,
+    array_format: ArrayFormat,
+    encodes_values: bool,
+) -> Option<FormatUrlError> {
+    let delimiter = match array_format {
+        ArrayFormat::Space => ' ',
+        ArrayFormat::Comma if !encodes_values => ',',
+        ArrayFormat::Pipe if !encodes_values => '|',
+        _ => return None,
+    };
+    query_params.iter().find_map(|(key, value)| {
+        find_ambiguous(value, delimiter).map(|element| FormatUrlError::InvalidQuery {
+            key: key.to_string(),
+            reason: format!("element {element:?} holds the {delimiter:?} separating elements"),
+        })
+    })
+}
+
+/// Nested lists are checked too, but only lists of scalars are joined into a single value.
+fn find_ambiguous<'v>(value: &'v QueryValue, delimiter: char) -> Option<&'v str> {
+    match value {
+        QueryValue::Scalar(_) | QueryValue::PreEncoded(_) => None,
+        QueryValue::Seq(values) if values.iter().all(QueryValue::is_scalar) => {
+            values.iter().find_map(|value| match value {
+                QueryValue::Scalar(scalar) if scalar.contains(delimiter) => Some(&**scalar),
+                _ => None,
+            })
+        }
+        QueryValue::Seq(values) => values
+            .iter()
+            .find_map(|value| find_ambiguous(value, delimiter)),
+        QueryValue::Map(entries) => entries
+            .iter()
+            .find_map(|(_, value)| find_ambiguous(value, delimiter)),
+    }
+}
+
+/// What to do with an added query parameter whose key is already in the base URL's query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Keep the existing parameter and add the new one after it, `?lang=en&lang=nl`.
+    #[default]
+    KeepBoth,
+    /// Drop the existing parameter, `?lang=nl`.
+    Override,
+    /// Make [`FormatUrl::try_format_url`](crate::FormatUrl::try_format_url) fail.
+    /// [`FormatUrl::format_url`](crate::FormatUrl::format_url) keeps the existing parameter and
+    /// leaves out the new one.
+    Error,
+}
+
+pub(crate) type QueryParams<'a> = Vec<(Cow<'a, str>, QueryValue<'a>)>;
+
+/// A possibly nested key, written out only once a scalar below it is.
+#[derive(Clone, Copy)]
+enum Key<'k> {
+    /// `name`
+    Name(&'k str),
+    /// `parent[name]`
+    Nested(&'k Key<'k>, &'k str),
+    /// `parent[index]`
+    Index(&'k Key<'k>, usize),
+    /// `parent[]`
+    Append(&'k Key<'k>),
+}
+
+impl Key<'_> {
+    fn write<W: fmt::Write + ?Sized>(
+        &self,
+        out: &mut W,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> fmt::Result {
+        match *self {
+            Key::Name(name) => write_encoded(out, name, encode_set),
+            Key::Nested(parent, name) => {
+                parent.write(out, encode_set)?;
+                out.write_char('[')?;
+                write_encoded(out, name, encode_set)?;
+                out.write_char(']')
+            }
+            Key::Index(parent, index) => {
+                parent.write(out, encode_set)?;
+                write!(out, "[{index}]")
+            }
+            Key::Append(parent) => {
+                parent.write(out, encode_set)?;
+                out.write_str("[]")
+            }
+        }
+    }
+}
+
+/// Writes `key=value` pairs, the first one preceded by `separator` and the others by `&`.
+struct PairWriter<'w, W: ?Sized> {
+    out: &'w mut W,
+    separator: &'static str,
+    key_set: Option<&'static AsciiSet>,
+    value_set: Option<&'static AsciiSet>,
+    array_format: ArrayFormat,
+}
+
+impl<W: fmt::Write + ?Sized> PairWriter<'_, W> {
+    fn start_pair(&mut self, key: &Key) -> fmt::Result {
+        self.out.write_str(self.separator)?;
+        self.separator = "&";
+        key.write(self.out, self.key_set)?;
+        self.out.write_char('=')
+    }
+
+    /// Write one element of a delimited list, with any delimiter in it percent-encoded so it can't
+    /// be taken for two elements.
+    fn write_element(&mut self, element: &str, delimiter: &str) -> fmt::Result {
+        if self.value_set.is_none() {
+            return self.out.write_str(element);
+        }
+        for (index, part) in element.split(delimiter).enumerate() {
+            if index > 0 {
+                PercentEncode::new(self.out, NON_ALPHANUMERIC).write_str(delimiter)?;
+            }
+            write_encoded(self.out, part, self.value_set)?;
+        }
+        Ok(())
+    }
+
+    /// Write out every scalar below `value`.
+    fn write_value(&mut self, key: &Key, value: &QueryValue) -> fmt::Result {
+        match value {
+            QueryValue::Scalar(scalar) => {
+                self.start_pair(key)?;
+                write_encoded(self.out, scalar, self.value_set)
+            }
+            QueryValue::PreEncoded(scalar) => {
+                self.start_pair(key)?;
+                self.out.write_str(scalar)
+            }
+            QueryValue::Map(entries) => entries.iter().try_for_each(|(sub_key, sub_value)| {
+                self.write_value(&Key::Nested(key, sub_key), sub_value)
+            }),
+            QueryValue::Seq(values) if values.is_empty() => Ok(()),
+            // Lists holding maps or lists always need an index to keep the elements apart.
+            QueryValue::Seq(values) if !values.iter().all(QueryValue::is_scalar) => values
+                .iter()
+                .enumerate()
+                .try_for_each(|(index, sub_value)| {
+                    self.write_value(&Key::Index(key, index), sub_value)
+                }),
+            QueryValue::Seq(values) => match self.array_format.delimiter() {
+                Some(delimiter) => {
+                    self.start_pair(key)?;
+                    for (index, sub_value) in values.iter().enumerate() {
+                        if index > 0 {
+                            self.out.write_str(delimiter)?;
+                        }
+                        match sub_value {
+                            QueryValue::Scalar(scalar) => self.write_element(scalar, delimiter)?,
+                            QueryValue::PreEncoded(scalar) => self.out.write_str(scalar)?,
+                            _ => {}
+                        }
+                    }
+                    Ok(())
+                }
+                None => {
+                    for (index, sub_value) in values.iter().enumerate() {
+                        match self.array_format {
+                            ArrayFormat::Indices => {
+                                self.write_value(&Key::Index(key, index), sub_value)?
+                            }
+                            ArrayFormat::Repeat => self.write_value(key, sub_value)?,
+                            _ => self.write_value(&Key::Append(key), sub_value)?,
+                        }
+                    }
+                    Ok(())
+                }
+            },
+        }
+    }
+}
+
+/// Write all parameters to `out`, preceded by `separator` unless there are none.
+pub(crate) fn write_query<W: fmt::Write + ?Sized>(
+    out: &mut W,
+    query_params: &QueryParams,
+    key_set: Option<&'static AsciiSet>,
+    value_set: Option<&'static AsciiSet>,
+    array_format: ArrayFormat,
+    separator: &'static str,
+) -> fmt::Result {
+    let mut writer = PairWriter {
+        out,
+        separator,
+        key_set,
+        value_set,
+        array_format,
+    };
+    query_params
+        .iter()
+        .try_for_each(|(key, value)| writer.write_value(&Key::Name(key), value))
+}
+
+/// Join all parameters into a query string, without the leading `?`.
+pub(crate) fn encode_query_string(
+    query_params: &QueryParams,
+    key_set: Option<&'static AsciiSet>,
+    value_set: Option<&'static AsciiSet>,
+    array_format: ArrayFormat,
+) -> String {
+    let mut out = String::new();
+    write_query(&mut out, query_params, key_set, value_set, array_format, "")
+        .expect("writing to a String can't fail");
+    out
+}
+
+/// The decoded key of a `key=value` pair.
+fn pair_key(pair: &str) -> Cow<'_, str> {
+    let key = pair.split_once('=').map_or(pair, |(key, _)| key);
+    percent_decode_str(key).decode_utf8_lossy()
+}
+
+/// Add the `added` query to the one already in the URL, `existing`, both without the leading `?`.
+/// Existing pairs are kept as written, apart from those dropped by [`DuplicateKeys::Override`].
+/// With [`DuplicateKeys::Error`] the first duplicate key is returned next to the query without any
+/// of the duplicates.
+pub(crate) fn merge_query(
+    existing: &str,
+    added: &str,
+    duplicate_keys: DuplicateKeys,
+) -> (String, Option<FormatUrlError>) {
+    if existing.is_empty() || added.is_empty() {
+        return ([existing, added].concat(), None);
+    }
+
+    let added_pairs: Vec<&str> = added.split('&').collect();
+    match duplicate_keys {
+        DuplicateKeys::KeepBoth => (format!("{existing}&{added}"), None),
+        DuplicateKeys::Override => {
+            let added_keys: Vec<_> = added_pairs.iter().map(|pair| pair_key(pair)).collect();
+            let merged: Vec<&str> = existing
+                .split('&')
+                .filter(|pair| !added_keys.contains(&pair_key(pair)))
+                .chain(added_pairs)
+                .collect();
+            (merged.join("&"), None)
+        }
+        DuplicateKeys::Error => {
+            let existing_keys: Vec<_> = existing.split('&').map(pair_key).collect();
+            let mut error = None;
+            let mut merged = vec![existing];
+            for pair in added_pairs {
+                let key = pair_key(pair);
+                if existing_keys.contains(&key) {
+                    error.get_or_insert_with(|| FormatUrlError::DuplicateQueryKey {
+                        key: key.into_owned(),
+                    });
+                } else {
+                    merged.push(pair);
+                }
+            }
+            (merged.join("&"), error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use crate::query::{
+        ambiguous_element, encode_query_string, merge_query, ArrayFormat, DuplicateKeys,
+        QueryParams, QueryValue,
+    };
+    use crate::{Component, FormatUrlError};
+
+    fn scalar(value: &str) -> QueryValue<'_> {
+        QueryValue::Scalar(Cow::Borrowed(value))
+    }
+
+    fn encoded(params: &QueryParams, array_format: ArrayFormat) -> String {
+        encode_query_string(
+            params,
+            Some(Component::QueryKey.default_encode_set()),
+            Some(Component::QueryValue.default_encode_set()),
+            array_format,
+        )
+    }
+
+    #[test]
+    fn flat_test() {
+        assert_eq!(
+            encoded(
+                &vec![("a".into(), scalar("1")), ("b c".into(), scalar("2+3"))],
+                ArrayFormat::default()
+            ),
+            "a=1&b%20c=2%2B3"
+        );
+    }
+
+    #[test]
+    fn nested_test() {
+        let params = vec![
+            (
+                "filter".into(),
+                QueryValue::Map(vec![
+                    ("status".into(), scalar("open")),
+                    (
+                        "labels".into(),
+                        QueryValue::Seq(vec![scalar("a"), scalar("b")]),
+                    ),
+                ]),
+            ),
+            (
+                "ids".into(),
+                QueryValue::Seq(vec![scalar("1"), scalar("2")]),
+            ),
+            (
+                "items".into(),
+                QueryValue::Seq(vec![
+                    QueryValue::Map(vec![("name".into(), scalar("x"))]),
+                    QueryValue::Map(vec![("name".into(), scalar("y"))]),
+                ]),
+            ),
+            ("empty".into(), QueryValue::Seq(Vec::new())),
+        ];
+
+        assert_eq!(
+            encode_query_string(&params, None, None, ArrayFormat::default()),
+            "filter[status]=open&filter[labels][]=a&filter[labels][]=b&ids[]=1&ids[]=2\
+             &items[0][name]=x&items[1][name]=y"
+        );
+    }
+
+    #[test]
+    fn nested_keys_are_encoded_test() {
+        let params = vec![(
+            "a b".into(),
+            QueryValue::Map(vec![("c]d".into(), scalar("e f"))]),
+        )];
+        assert_eq!(
+            encoded(&params, ArrayFormat::default()),
+            "a%20b[c%5Dd]=e%20f"
+        );
+    }
+
+    #[test]
+    fn array_formats_test() {
+        let params = vec![
+            (
+                "ids".into(),
+                QueryValue::Seq(vec![scalar("1"), scalar("2 3")]),
+            ),
+            (
+                "filter".into(),
+                QueryValue::Map(vec![(
+                    "labels".into(),
+                    QueryValue::Seq(vec![scalar("a"), scalar("b")]),
+                )]),
+            ),
+        ];
+        let cases = [
+            (
+                ArrayFormat::Brackets,
+                "ids[]=1&ids[]=2%203&filter[labels][]=a&filter[labels][]=b",
+            ),
+            (
+                ArrayFormat::Indices,
+                "ids[0]=1&ids[1]=2%203&filter[labels][0]=a&filter[labels][1]=b",
+            ),
+            (
+                ArrayFormat::Repeat,
+                "ids=1&ids=2%203&filter[labels]=a&filter[labels]=b",
+            ),
+            (ArrayFormat::Comma, "ids=1,2%203&filter[labels]=a,b"),
+            (ArrayFormat::Pipe, "ids=1|2%203&filter[labels]=a|b"),
+        ];
+
+        for (array_format, expected) in cases {
+            assert_eq!(encoded(&params, array_format), expected, "{array_format:?}");
+        }
+    }
+
+    #[test]
+    fn delimiter_in_element_test() {
+        let params = vec![(
+            "ids".into(),
+            QueryValue::Seq(vec![scalar("1,2"), scalar("3|4")]),
+        )];
+        assert_eq!(encoded(&params, ArrayFormat::Comma), "ids=1%2C2,3%7C4");
+        assert_eq!(encoded(&params, ArrayFormat::Pipe), "ids=1,2|3%7C4");
+    }
+
+    #[test]
+    fn space_delimited_test() {
+        let params = vec![(
+            "filter".into(),
+            QueryValue::Map(vec![(
+                "labels".into(),
+                QueryValue::Seq(vec![scalar("a"), scalar("b")]),
+            )]),
+        )];
+        assert_eq!(encoded(&params, ArrayFormat::Space), "filter[labels]=a%20b");
+        assert_eq!(ambiguous_element(&params, ArrayFormat::Space, true), None);
+    }
+
+    #[test]
+    fn ambiguous_element_test() {
+        let params = vec![(
+            "ids".into(),
+            QueryValue::Seq(vec![scalar("1,2"), scalar("3 4")]),
+        )];
+        assert_eq!(
+            ambiguous_element(&params, ArrayFormat::Space, true),
+            Some(FormatUrlError::InvalidQuery {
+                key: "ids".to_string(),
+                reason: "element \"3 4\" holds the ' ' separating elements".to_string(),
+            })
+        );
+        assert_eq!(ambiguous_element(&params, ArrayFormat::Comma, true), None);
+        assert!(ambiguous_element(&params, ArrayFormat::Comma, false).is_some());
+        assert_eq!(ambiguous_element(&params, ArrayFormat::Repeat, false), None);
+    }
+
+    #[test]
+    fn array_format_with_nested_elements_test() {
+        let params = vec![(
+            "items".into(),
+            QueryValue::Seq(vec![QueryValue::Map(vec![("name".into(), scalar("x"))])]),
+        )];
+        assert_eq!(encoded(&params, ArrayFormat::Comma), "items[0][name]=x");
+    }
+
+    #[test]
+    fn merge_query_test() {
+        let cases = [
+            (
+                DuplicateKeys::KeepBoth,
+                "lang=en&a%20b=1&page=1&lang=nl&a b=2",
+            ),
+            (DuplicateKeys::Override, "page=1&lang=nl&a b=2"),
+            (DuplicateKeys::Error, "lang=en&a%20b=1&page=1"),
+        ];
+        for (duplicate_keys, expected) in cases {
+            assert_eq!(
+                merge_query("lang=en&a%20b=1&page=1", "lang=nl&a b=2", duplicate_keys).0,
+                expected,
+                "{duplicate_keys:?}"
+            );
+        }
+        assert_eq!(
+            merge_query("lang=en", "lang=nl", DuplicateKeys::Error).1,
+            Some(FormatUrlError::DuplicateQueryKey {
+                key: "lang".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn merge_empty_query_test() {
+        assert_eq!(
+            merge_query("", "a=1", DuplicateKeys::Error),
+            ("a=1".to_string(), None)
+        );
+        assert_eq!(
+            merge_query("sig=a%2Bb&&x", "", DuplicateKeys::Override),
+            ("sig=a%2Bb&&x".to_string(), None)
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Building `reqwest` requests from a [`FormatUrl`].
+
+use reqwest::{Client, Method, RequestBuilder};
+
+use crate::{FormatUrl, FormatUrlError};
+
+impl FormatUrl<'_> {
+    /// Start a request to the formatted URL. The URL is validated like
+    /// [`FormatUrl::try_format_url`] does and handed to `reqwest` as a [`url::Url`].
+    pub fn into_request(
+        self,
+        method: Method,
+        client: &Client,
+    ) -> Result<RequestBuilder, FormatUrlError> {
+        Ok(client.request(method, self.format_url_as_url()?))
+    }
+}
+
+/// Start requests on a [`Client`] straight from a [`FormatUrl`].
+///
+/// ```no_run
+/// use format_url::{ClientExt, FormatUrl};
+///
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = reqwest::Client::new();
+/// let response = client
+///     .get_formatted(
+///         FormatUrl::new("https://api.example.com")
+///             .with_path_template("/user/:name")
+///             .with_substitutes(vec![("name", "alex")]),
+///     )?
+///     .send()
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait ClientExt {
+    /// Like [`Client::request`], for the URL `url` formats to.
+    fn request_formatted(
+        &self,
+        method: Method,
+        url: FormatUrl<'_>,
+    ) -> Result<RequestBuilder, FormatUrlError>;
+
+    /// Like [`Client::get`], for the URL `url` formats to.
+    fn get_formatted(&self, url: FormatUrl<'_>) -> Result<RequestBuilder, FormatUrlError> {
+        self.request_formatted(Method::GET, url)
+    }
+
+    /// Like [`Client::post`], for the URL `url` formats to.
+    fn post_formatted(&self, url: FormatUrl<'_>) -> Result<RequestBuilder, FormatUrlError> {
+        self.request_formatted(Method::POST, url)
+    }
+}
+
+impl ClientExt for Client {
+    fn request_formatted(
+        &self,
+        method: Method,
+        url: FormatUrl<'_>,
+    ) -> Result<RequestBuilder, FormatUrlError> {
+        url.into_request(method, self)
+    }
+}
@@ -0,0 +1,605 @@
+//! Serialize any `serde::Serialize` value into query parameters.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::query::{QueryParams, QueryValue};
+use crate::{FormatUrlError, PreEncoded, UrlValue};
+
+/// The newtype struct name [`PreEncoded`] serializes as, so it can be told apart from others.
+const PRE_ENCODED: &str = "$format_url::PreEncoded";
+
+impl<T: Serialize> Serialize for PreEncoded<T> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_newtype_struct(PRE_ENCODED, &self.0)
+    }
+}
+
+/// A serialized substitute, kept as is when it was wrapped in [`PreEncoded`].
+pub(crate) struct Substitute {
+    text: String,
+    pre_encoded: bool,
+}
+
+impl UrlValue for Substitute {
+    fn fmt_text(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+
+    fn is_pre_encoded(&self) -> bool {
+        self.pre_encoded
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Error {
+    key: Option<String>,
+    reason: String,
+}
+
+impl Error {
+    fn new(reason: String) -> Self {
+        Self { key: None, reason }
+    }
+
+    /// Remember the key of the innermost pair that failed.
+    fn at(mut self, key: &str) -> Self {
+        self.key.get_or_insert_with(|| key.to_string());
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.reason)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Error::new(msg.to_string())
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::new(format!("{what} can't be used as a query value"))
+}
+
+fn top_level() -> Error {
+    Error::new(
+        "expected a struct, map or sequence of (key, value) pairs at the top level".to_string(),
+    )
+}
+
+/// Serialize `value`, which must be a struct, map, sequence of pairs or unit, into query
+/// parameters. Fields that are `None` are skipped, nested structs, maps and sequences are kept
+/// as nested values.
+pub(crate) fn to_query<T: Serialize + ?Sized>(
+    value: &T,
+) -> Result<QueryParams<'static>, FormatUrlError> {
+    into_params(value.serialize(ValueSerializer)).map_err(|error| FormatUrlError::InvalidQuery {
+        key: error.key.unwrap_or_default(),
+        reason: error.reason,
+    })
+}
+
+/// Serialize `value` like [`to_query`], but require every value to be a scalar.
+pub(crate) fn to_substitutes<T: Serialize + ?Sized>(
+    value: &T,
+) -> Result<Vec<(String, Substitute)>, FormatUrlError> {
+    let invalid = |error: Error| FormatUrlError::InvalidSubstitute {
+        key: error.key.unwrap_or_default(),
+        reason: error.reason,
+    };
+
+    into_params(value.serialize(ValueSerializer))
+        .map_err(invalid)?
+        .into_iter()
+        .map(|(key, value)| match value {
+            QueryValue::Scalar(text) => Ok((
+                key.into_owned(),
+                Substitute {
+                    text: text.into_owned(),
+                    pre_encoded: false,
+                },
+            )),
+            QueryValue::PreEncoded(text) => Ok((
+                key.into_owned(),
+                Substitute {
+                    text: text.into_owned(),
+                    pre_encoded: true,
+                },
+            )),
+            _ => Err(invalid(
+                Error::new("expected a string, number, bool or unit variant".to_string()).at(&key),
+            )),
+        })
+        .collect()
+}
+
+fn into_params(
+    value: Result<Option<QueryValue<'static>>, Error>,
+) -> Result<QueryParams<'static>, Error> {
+    match value? {
+        None => Ok(Vec::new()),
+        Some(QueryValue::Map(entries)) => Ok(entries),
+        Some(QueryValue::Seq(pairs)) => pairs
+            .into_iter()
+            .filter_map(|pair| match pair {
+                QueryValue::Seq(mut pair) => match (pair.pop(), pair.pop(), pair.pop()) {
+                    (Some(value), Some(QueryValue::Scalar(key)), None) => Some(Ok((key, value))),
+                    // Sequences drop elements that are `None`, so this was a `(key, None)` pair.
+                    (Some(QueryValue::Scalar(_)), None, None) => None,
+                    _ => Some(Err(top_level())),
+                },
+                _ => Some(Err(top_level())),
+            })
+            .collect(),
+        Some(QueryValue::Scalar(_) | QueryValue::PreEncoded(_)) => Err(top_level()),
+    }
+}
+
+fn scalar(value: impl ToString) -> Result<Option<QueryValue<'static>>, Error> {
+    Ok(Some(QueryValue::Scalar(Cow::Owned(value.to_string()))))
+}
+
+/// Serializes a value into a query value. `None` means the value should be skipped.
+struct ValueSerializer;
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+    type SerializeSeq = SeqSerializer;
+    type SerializeTuple = SeqSerializer;
+    type SerializeTupleStruct = SeqSerializer;
+    type SerializeTupleVariant = Impossible<Self::Ok, Error>;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = Impossible<Self::Ok, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Error> {
+        scalar(v)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, Error> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Error> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Error> {
+        scalar(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Error> {
+        match value.serialize(self)? {
+            Some(QueryValue::Scalar(text)) if name == PRE_ENCODED => {
+                Ok(Some(QueryValue::PreEncoded(text)))
+            }
+            value => Ok(value),
+        }
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer, Error> {
+        Ok(SeqSerializer {
+            values: Vec::with_capacity(len.unwrap_or_default()),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer, Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer, Error> {
+        Ok(MapSerializer {
+            entries: Vec::with_capacity(len.unwrap_or_default()),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<MapSerializer, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported("an enum variant with data"))
+    }
+}
+
+struct SeqSerializer {
+    values: Vec<QueryValue<'static>>,
+}
+
+impl ser::SerializeSeq for SeqSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        if let Some(value) = value.serialize(ValueSerializer)? {
+            self.values.push(value);
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(QueryValue::Seq(self.values)))
+    }
+}
+
+impl ser::SerializeTuple for SeqSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for SeqSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct MapSerializer {
+    entries: QueryParams<'static>,
+    key: Option<String>,
+}
+
+impl MapSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        if let Some(value) = value
+            .serialize(ValueSerializer)
+            .map_err(|error| error.at(&key))?
+        {
+            self.entries.push((Cow::Owned(key), value));
+        }
+        Ok(())
+    }
+}
+
+impl ser::SerializeMap for MapSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), Error> {
+        match key.serialize(ValueSerializer)? {
+            Some(QueryValue::Scalar(key)) => {
+                self.key = Some(key.into_owned());
+                Ok(())
+            }
+            _ => Err(Error::new(
+                "query keys must be strings or numbers".to_string(),
+            )),
+        }
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| Error::new("map value serialized before its key".to_string()))?;
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        Ok(Some(QueryValue::Map(self.entries)))
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Option<QueryValue<'static>>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(key.to_string(), value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Error> {
+        ser::SerializeMap::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::Serialize;
+
+    use crate::query::{encode_query_string, ArrayFormat};
+    use crate::ser::to_query;
+    use crate::{Component, FormatUrlError, PreEncoded};
+
+    fn query_string<T: Serialize + ?Sized>(value: &T) -> Result<String, FormatUrlError> {
+        to_query(value)
+            .map(|params| encode_query_string(&params, None, None, ArrayFormat::default()))
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum State {
+        Open,
+    }
+
+    #[derive(Serialize)]
+    struct Search<'a> {
+        q: &'a str,
+        page: u32,
+        ratio: f64,
+        archived: bool,
+        state: State,
+        label: Option<&'a str>,
+        sort: Option<&'a str>,
+    }
+
+    #[test]
+    fn struct_test() {
+        assert_eq!(
+            query_string(&Search {
+                q: "rust",
+                page: 2,
+                ratio: 0.5,
+                archived: false,
+                state: State::Open,
+                label: None,
+                sort: Some("stars"),
+            }),
+            Ok("q=rust&page=2&ratio=0.5&archived=false&state=open&sort=stars".to_string())
+        );
+    }
+
+    #[test]
+    fn map_test() {
+        let map = BTreeMap::from([("b", 2), ("a", 1)]);
+        assert_eq!(query_string(&map), Ok("a=1&b=2".to_string()));
+    }
+
+    #[test]
+    fn tuples_test() {
+        assert_eq!(
+            query_string(&[("a", "1"), ("a", "2")]),
+            Ok("a=1&a=2".to_string())
+        );
+        assert_eq!(
+            query_string(&vec![("a", Some(1)), ("b", None)]),
+            Ok("a=1".to_string())
+        );
+    }
+
+    #[test]
+    fn pre_encoded_test() {
+        #[derive(Serialize)]
+        struct Page {
+            cursor: PreEncoded<&'static str>,
+            ids: Vec<PreEncoded<&'static str>>,
+        }
+
+        let params = to_query(&Page {
+            cursor: PreEncoded("a%2Bb"),
+            ids: vec![PreEncoded("1%2C2")],
+        })
+        .unwrap();
+        assert_eq!(
+            encode_query_string(
+                &params,
+                Some(Component::QueryKey.default_encode_set()),
+                Some(Component::QueryValue.default_encode_set()),
+                ArrayFormat::Comma
+            ),
+            "cursor=a%2Bb&ids=1%2C2"
+        );
+    }
+
+    #[test]
+    fn unit_test() {
+        assert_eq!(query_string(&()), Ok(String::new()));
+    }
+
+    #[test]
+    fn top_level_scalar_test() {
+        assert!(matches!(
+            query_string(&"q"),
+            Err(FormatUrlError::InvalidQuery { .. })
+        ));
+        assert!(matches!(
+            query_string(&[1, 2]),
+            Err(FormatUrlError::InvalidQuery { .. })
+        ));
+    }
+
+    #[test]
+    fn nested_test() {
+        #[derive(Serialize)]
+        struct Filter {
+            status: State,
+            labels: Vec<&'static str>,
+        }
+
+        #[derive(Serialize)]
+        struct Item {
+            name: &'static str,
+        }
+
+        #[derive(Serialize)]
+        struct Query {
+            filter: Filter,
+            ids: Vec<u32>,
+            items: Vec<Item>,
+            tags: Option<Vec<&'static str>>,
+        }
+
+        assert_eq!(
+            query_string(&Query {
+                filter: Filter {
+                    status: State::Open,
+                    labels: vec!["bug", "ui"],
+                },
+                ids: vec![1, 2],
+                items: vec![Item { name: "x" }, Item { name: "y" }],
+                tags: None,
+            }),
+            Ok(
+                "filter[status]=open&filter[labels][]=bug&filter[labels][]=ui&ids[]=1&ids[]=2\
+                &items[0][name]=x&items[1][name]=y"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn unsupported_value_test() {
+        #[derive(Serialize)]
+        enum Shape {
+            Circle(u8),
+        }
+
+        #[derive(Serialize)]
+        struct Query {
+            a: u8,
+            filter: BTreeMap<&'static str, Shape>,
+        }
+
+        assert_eq!(
+            to_query(&Query {
+                a: 1,
+                filter: BTreeMap::from([("shape", Shape::Circle(1))]),
+            }),
+            Err(FormatUrlError::InvalidQuery {
+                key: "shape".to_string(),
+                reason: "an enum variant with data can't be used as a query value".to_string()
+            })
+        );
+    }
+}
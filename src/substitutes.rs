@@ -0,0 +1,285 @@
+//! Where path template substitutes come from.
+
+use std::borrow::{Borrow, Cow};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+
+use percent_encoding::AsciiSet;
+
+use crate::encode::{write_encoded, write_value, Text};
+use crate::UrlValue;
+
+/// Anything placeholders can be filled from: slices, arrays and vecs of pairs, `HashMap` and
+/// `BTreeMap`. Values are stringified using their `Display` implementation, so integers, UUIDs
+/// and the like can be used directly. Values wrapped in [`PreEncoded`](crate::PreEncoded) are not
+/// percent-encoded again, see [`UrlValue`].
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use format_url::Template;
+///
+/// let template = Template::parse("/repos/:owner/issues/:number");
+/// let substitutes = HashMap::from([("owner", "alextes".to_string()), ("number", 7.to_string())]);
+/// assert_eq!(template.render(&substitutes), "/repos/alextes/issues/7");
+/// assert_eq!(template.render(&[("owner", "alextes")]), "/repos/alextes/issues/:number");
+/// ```
+pub trait SubstituteSource {
+    /// The substitute for the placeholder called `key`, if any.
+    fn get(&self, key: &str) -> Option<Cow<'_, str>>;
+
+    /// Every key in this source, in order. Used to find substitutes no placeholder asks for.
+    fn keys(&self) -> Vec<Cow<'_, str>>;
+
+    /// Write the substitute for `key` to `out`, percent-encoded with `encode_set` if there is one,
+    /// or return `None` when there is no substitute. Sources holding values that aren't strings
+    /// can override this to write them without allocating, or to leave pre-encoded values as
+    /// they are.
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        self.get(key)
+            .map(|value| write_encoded(out, &value, encode_set))
+    }
+
+    /// Whether the substitute for `key` is already percent-encoded and written as is, see
+    /// [`PreEncoded`](crate::PreEncoded).
+    fn is_pre_encoded(&self, _key: &str) -> bool {
+        false
+    }
+}
+
+/// No substitutes at all.
+impl SubstituteSource for () {
+    fn get(&self, _key: &str) -> Option<Cow<'_, str>> {
+        None
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        Vec::new()
+    }
+}
+
+impl<T: SubstituteSource + ?Sized> SubstituteSource for &T {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        (**self).get(key)
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        (**self).keys()
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        (**self).write_substitute(key, out, encode_set)
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        (**self).is_pre_encoded(key)
+    }
+}
+
+/// When a key appears more than once, the first pair wins.
+impl<K: AsRef<str>, V: UrlValue> SubstituteSource for [(K, V)] {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        self.iter()
+            .find(|(candidate, _)| candidate.as_ref() == key)
+            .map(|(_, value)| Cow::Owned(Text(value).to_string()))
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        self.iter()
+            .map(|(key, _)| Cow::Borrowed(key.as_ref()))
+            .collect()
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        self.iter()
+            .find(|(candidate, _)| candidate.as_ref() == key)
+            .map(|(_, value)| write_value(out, value, encode_set))
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        self.iter()
+            .find(|(candidate, _)| candidate.as_ref() == key)
+            .is_some_and(|(_, value)| value.is_pre_encoded())
+    }
+}
+
+impl<K: AsRef<str>, V: UrlValue, const N: usize> SubstituteSource for [(K, V); N] {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        SubstituteSource::get(self.as_slice(), key)
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        SubstituteSource::keys(self.as_slice())
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        self.as_slice().write_substitute(key, out, encode_set)
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        self.as_slice().is_pre_encoded(key)
+    }
+}
+
+impl<K: AsRef<str>, V: UrlValue> SubstituteSource for Vec<(K, V)> {
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        SubstituteSource::get(self.as_slice(), key)
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        SubstituteSource::keys(self.as_slice())
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        self.as_slice().write_substitute(key, out, encode_set)
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        self.as_slice().is_pre_encoded(key)
+    }
+}
+
+impl<K, V, S> SubstituteSource for HashMap<K, V, S>
+where
+    K: Borrow<str> + Eq + Hash,
+    V: UrlValue,
+    S: BuildHasher,
+{
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        HashMap::get(self, key).map(|value| Cow::Owned(Text(value).to_string()))
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        HashMap::keys(self)
+            .map(|key| Cow::Borrowed(key.borrow()))
+            .collect()
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        HashMap::get(self, key).map(|value| write_value(out, value, encode_set))
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        HashMap::get(self, key).is_some_and(UrlValue::is_pre_encoded)
+    }
+}
+
+impl<K, V> SubstituteSource for BTreeMap<K, V>
+where
+    K: Borrow<str> + Ord,
+    V: UrlValue,
+{
+    fn get(&self, key: &str) -> Option<Cow<'_, str>> {
+        BTreeMap::get(self, key).map(|value| Cow::Owned(Text(value).to_string()))
+    }
+
+    fn keys(&self) -> Vec<Cow<'_, str>> {
+        BTreeMap::keys(self)
+            .map(|key| Cow::Borrowed(key.borrow()))
+            .collect()
+    }
+
+    fn write_substitute(
+        &self,
+        key: &str,
+        out: &mut dyn fmt::Write,
+        encode_set: Option<&'static AsciiSet>,
+    ) -> Option<fmt::Result> {
+        BTreeMap::get(self, key).map(|value| write_value(out, value, encode_set))
+    }
+
+    fn is_pre_encoded(&self, key: &str) -> bool {
+        BTreeMap::get(self, key).is_some_and(UrlValue::is_pre_encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::{Component, PreEncoded, SubstituteSource};
+
+    #[test]
+    fn pairs_test() {
+        let pairs = vec![("id", 1), ("id", 2), ("page", 3)];
+        assert_eq!(pairs.get("id").as_deref(), Some("1"));
+        assert_eq!(pairs.get("missing"), None);
+        assert_eq!(pairs.keys(), vec!["id", "id", "page"]);
+    }
+
+    #[test]
+    fn write_substitute_test() {
+        let mut out = String::new();
+        assert_eq!(
+            [("id", 7)].write_substitute("id", &mut out, None),
+            Some(Ok(()))
+        );
+        assert_eq!(
+            [("id", 7)].write_substitute("missing", &mut out, None),
+            None
+        );
+        assert_eq!(out, "7");
+    }
+
+    #[test]
+    fn pre_encoded_test() {
+        let encode_set = Some(Component::PathSegment.default_encode_set());
+        let mut out = String::new();
+        let pairs = [("a", PreEncoded("x%2Fy z")), ("b", PreEncoded("1"))];
+        pairs.write_substitute("a", &mut out, encode_set);
+        assert_eq!(out, "x%2Fy z");
+        assert_eq!(pairs.get("a").as_deref(), Some("x%2Fy z"));
+        assert!(pairs.is_pre_encoded("a"));
+
+        let mut out = String::new();
+        let map = HashMap::from([("a", "x/y")]);
+        map.write_substitute("a", &mut out, encode_set);
+        assert_eq!(out, "x%2Fy");
+        assert!(!map.is_pre_encoded("a"));
+    }
+
+    #[test]
+    fn hash_map_test() {
+        let map = HashMap::from([("id".to_string(), 1u64)]);
+        assert_eq!(SubstituteSource::get(&map, "id").as_deref(), Some("1"));
+        assert_eq!(SubstituteSource::keys(&map), vec!["id"]);
+    }
+
+    #[test]
+    fn btree_map_test() {
+        let map = BTreeMap::from([("b", "2"), ("a", "1")]);
+        assert_eq!(SubstituteSource::get(&map, "a").as_deref(), Some("1"));
+        assert_eq!(SubstituteSource::keys(&map), vec!["a", "b"]);
+    }
+}
@@ -0,0 +1,1109 @@
+//! Path templates that are parsed once and rendered many times.
+
+use std::fmt;
+use std::str::FromStr;
+
+use format_url_grammar::{self as grammar, Modifier, Token, Tokens};
+use percent_encoding::{percent_decode_str, AsciiSet};
+
+use crate::constraint::Constraint;
+use crate::encode::{write_encoded, EscapeDots};
+use crate::{Component, Constraints, FormatUrlError, SubstituteSource};
+
+/// Which placeholder notations a [`Template`] recognizes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PlaceholderSyntax {
+    /// `:name`, as in `/user/:id`, and `*name` for splats.
+    #[default]
+    Colon,
+    /// `{name}`, as in OpenAPI paths and axum routes, and `{+name}` for splats.
+    Braces,
+    /// Both `:name` and `{name}`.
+    Both,
+}
+
+impl PlaceholderSyntax {
+    fn colon(self) -> bool {
+        matches!(self, PlaceholderSyntax::Colon | PlaceholderSyntax::Both)
+    }
+
+    fn braces(self) -> bool {
+        matches!(self, PlaceholderSyntax::Braces | PlaceholderSyntax::Both)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// The position is the byte offset of the `:`, `*` or `{` in the template.
+    Placeholder {
+        name: String,
+        position: usize,
+        /// The placeholder as written in the template, kept when there's no substitute for it.
+        written: String,
+        /// Written `*name` or `{+name}`, keeping the `/` in its substitute.
+        splat: bool,
+        /// Written `:name=default` or `{name=default}`, used when there is no substitute.
+        default: Option<String>,
+        /// Written `:name(pattern)` or `{name:constraint}`.
+        constraint: Option<Constraint>,
+    },
+    /// Written `(/...)`, or `:name?` for a single placeholder, and left out as a whole when a
+    /// placeholder in it has no substitute.
+    Group(Vec<Segment>),
+}
+
+impl Segment {
+    fn placeholders(&self) -> Vec<&str> {
+        match self {
+            Segment::Literal(_) => Vec::new(),
+            Segment::Placeholder { name, .. } => vec![name],
+            Segment::Group(segments) => segments.iter().flat_map(Segment::placeholders).collect(),
+        }
+    }
+}
+
+/// Segments as they're parsed, going into the open group, if any.
+#[derive(Default)]
+struct Segments {
+    segments: Vec<Segment>,
+    group: Option<Vec<Segment>>,
+    literal: String,
+}
+
+impl Segments {
+    fn current(&mut self) -> &mut Vec<Segment> {
+        self.group.as_mut().unwrap_or(&mut self.segments)
+    }
+
+    fn push_literal(&mut self) {
+        if !self.literal.is_empty() {
+            let literal = Segment::Literal(std::mem::take(&mut self.literal));
+            self.current().push(literal);
+        }
+    }
+
+    fn push(&mut self, segment: Segment) {
+        self.push_literal();
+        self.current().push(segment);
+    }
+
+    /// Add a `:name?` placeholder, along with the `/` before it, as a group of its own unless it's
+    /// in one already.
+    fn push_optional(&mut self, placeholder: Segment) {
+        if self.group.is_some() {
+            return self.push(placeholder);
+        }
+        let mut group = Vec::new();
+        if self.literal.ends_with('/') {
+            self.literal.pop();
+            group.push(Segment::Literal("/".to_string()));
+        }
+        group.push(placeholder);
+        self.push(Segment::Group(group));
+    }
+
+    fn open_group(&mut self) {
+        self.push_literal();
+        self.group = Some(Vec::new());
+    }
+
+    fn close_group(&mut self) {
+        self.push_literal();
+        let group = self.group.take().unwrap_or_default();
+        self.segments.push(Segment::Group(group));
+    }
+
+    fn finish(mut self) -> Vec<Segment> {
+        self.push_literal();
+        self.segments
+    }
+}
+
+/// How substitutes are written while rendering a [`Template`].
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RenderOptions<'c> {
+    /// Percent-encode substitutes with this set, or not at all.
+    pub(crate) encode_set: Option<&'static AsciiSet>,
+    /// Let splat substitutes hold `..` segments and other substitutes be `.` or `..`.
+    pub(crate) allow_dot_segments: bool,
+    /// Where to look up named constraints, besides the ones that are always available.
+    pub(crate) constraints: Option<&'c Constraints>,
+}
+
+impl RenderOptions<'_> {
+    pub(crate) fn new(encode_set: Option<&'static AsciiSet>) -> Self {
+        Self {
+            encode_set,
+            allow_dot_segments: false,
+            constraints: None,
+        }
+    }
+}
+
+/// The substitute for a splat placeholder.
+enum Splat {
+    Missing,
+    /// Holds a `..` segment, which isn't allowed.
+    DotSegment,
+    Value {
+        text: String,
+        pre_encoded: bool,
+    },
+}
+
+impl Splat {
+    fn get<S: SubstituteSource + ?Sized>(
+        substitutes: &S,
+        name: &str,
+        options: RenderOptions,
+    ) -> Self {
+        let mut text = String::new();
+        if substitutes
+            .write_substitute(name, &mut text, None)
+            .is_none()
+        {
+            return Splat::Missing;
+        }
+        let pre_encoded = substitutes.is_pre_encoded(name);
+        // Pre-encoded segments are decoded first, `.%2E` is just as much a `..` as `%2E%2E`.
+        let is_dot_segment = |segment: &str| {
+            if pre_encoded {
+                percent_decode_str(segment).eq(*b"..")
+            } else {
+                segment == ".."
+            }
+        };
+        if !options.allow_dot_segments && text.split('/').any(is_dot_segment) {
+            return Splat::DotSegment;
+        }
+        Splat::Value { text, pre_encoded }
+    }
+}
+
+/// Write a splat substitute, encoding each segment on its own and keeping the `/` between them.
+fn write_splat<W: fmt::Write>(
+    out: &mut W,
+    value: &str,
+    pre_encoded: bool,
+    encode_set: Option<&'static AsciiSet>,
+) -> fmt::Result {
+    if pre_encoded || encode_set.is_none() {
+        return out.write_str(value);
+    }
+    for (index, segment) in value.split('/').enumerate() {
+        if index > 0 {
+            out.write_char('/')?;
+        }
+        write_encoded(out, segment, encode_set)?;
+    }
+    Ok(())
+}
+
+/// Whether there is a substitute for `name` that isn't empty.
+fn is_filled<S: SubstituteSource + ?Sized>(substitutes: &S, name: &str) -> bool {
+    substitutes.get(name).is_some_and(|value| !value.is_empty())
+}
+
+/// Whether every placeholder in a group that has no default has a substitute, so the group is
+/// written.
+fn fills_group<S: SubstituteSource + ?Sized>(segments: &[Segment], substitutes: &S) -> bool {
+    segments.iter().all(|segment| match segment {
+        Segment::Literal(_) => true,
+        Segment::Placeholder {
+            name,
+            default: None,
+            ..
+        } => is_filled(substitutes, name),
+        Segment::Placeholder { .. } => true,
+        Segment::Group(group) => fills_group(group, substitutes),
+    })
+}
+
+fn write_segments<S, W>(
+    segments: &[Segment],
+    substitutes: &S,
+    options: RenderOptions,
+    out: &mut W,
+) -> fmt::Result
+where
+    S: SubstituteSource + ?Sized,
+    W: fmt::Write,
+{
+    for segment in segments {
+        match segment {
+            Segment::Literal(literal) => out.write_str(literal)?,
+            Segment::Group(group) => {
+                if fills_group(group, substitutes) {
+                    write_segments(group, substitutes, options, out)?
+                }
+            }
+            Segment::Placeholder {
+                name,
+                default: Some(default),
+                ..
+            } if !is_filled(substitutes, name) => out.write_str(default)?,
+            Segment::Placeholder {
+                name,
+                written,
+                constraint: Some(constraint),
+                ..
+            } if check_constraint(substitutes, name, constraint, options) != Ok(true) => {
+                out.write_str(written)?
+            }
+            Segment::Placeholder {
+                name,
+                written,
+                splat: true,
+                ..
+            } => match Splat::get(substitutes, name, options) {
+                Splat::Value { text, pre_encoded } => {
+                    write_splat(out, &text, pre_encoded, options.encode_set)?
+                }
+                Splat::Missing | Splat::DotSegment => out.write_str(written)?,
+            },
+            Segment::Placeholder { name, written, .. } if options.allow_dot_segments => {
+                match substitutes.write_substitute(name, out, options.encode_set) {
+                    Some(result) => result?,
+                    None => out.write_str(written)?,
+                }
+            }
+            Segment::Placeholder { name, written, .. } => {
+                let mut escaped = EscapeDots::new(&mut *out);
+                match substitutes.write_substitute(name, &mut escaped, options.encode_set) {
+                    Some(result) => {
+                        result?;
+                        escaped.finish()?
+                    }
+                    None => out.write_str(written)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn try_render_segments<S: SubstituteSource + ?Sized>(
+    segments: &[Segment],
+    substitutes: &S,
+    options: RenderOptions,
+    rendered: &mut String,
+) -> Result<(), FormatUrlError> {
+    for segment in segments {
+        if let Segment::Placeholder {
+            name,
+            position,
+            constraint: Some(constraint),
+            ..
+        } = segment
+        {
+            match check_constraint(substitutes, name, constraint, options) {
+                Ok(true) => {}
+                Ok(false) => {
+                    return Err(FormatUrlError::ConstraintMismatch {
+                        key: name.clone(),
+                        constraint: constraint.to_string(),
+                        position: *position,
+                    })
+                }
+                Err(reason) => {
+                    return Err(FormatUrlError::MalformedTemplate {
+                        reason,
+                        position: *position,
+                    })
+                }
+            }
+        }
+
+        match segment {
+            Segment::Literal(literal) => rendered.push_str(literal),
+            Segment::Group(group) => {
+                if fills_group(group, substitutes) {
+                    try_render_segments(group, substitutes, options, rendered)?
+                }
+            }
+            Segment::Placeholder {
+                name,
+                default: Some(default),
+                ..
+            } if !is_filled(substitutes, name) => rendered.push_str(default),
+            Segment::Placeholder {
+                name,
+                position,
+                splat: true,
+                ..
+            } => match Splat::get(substitutes, name, options) {
+                Splat::Value { text, .. } if text.is_empty() => {
+                    return Err(FormatUrlError::EmptySegment {
+                        key: name.clone(),
+                        position: *position,
+                    })
+                }
+                Splat::Value { text, pre_encoded } => {
+                    write_splat(rendered, &text, pre_encoded, options.encode_set)
+                        .expect("writing to a String can't fail")
+                }
+                Splat::Missing => {
+                    return Err(FormatUrlError::MissingSubstitute {
+                        key: name.clone(),
+                        position: *position,
+                    })
+                }
+                Splat::DotSegment => {
+                    return Err(FormatUrlError::DotSegment {
+                        key: name.clone(),
+                        position: *position,
+                    })
+                }
+            },
+            Segment::Placeholder { name, position, .. } => {
+                let start = rendered.len();
+                match substitutes.write_substitute(name, rendered, options.encode_set) {
+                    Some(_) if rendered.len() == start => {
+                        return Err(FormatUrlError::EmptySegment {
+                            key: name.clone(),
+                            position: *position,
+                        })
+                    }
+                    Some(_)
+                        if !options.allow_dot_segments
+                            && matches!(&rendered[start..], "." | "..") =>
+                    {
+                        return Err(FormatUrlError::DotSegment {
+                            key: name.clone(),
+                            position: *position,
+                        })
+                    }
+                    Some(result) => result.expect("writing to a String can't fail"),
+                    None => {
+                        return Err(FormatUrlError::MissingSubstitute {
+                            key: name.clone(),
+                            position: *position,
+                        })
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether the substitute for `name` passes `constraint`, or why it can't be checked. Missing and
+/// empty substitutes pass, those are caught by rendering.
+fn check_constraint<S: SubstituteSource + ?Sized>(
+    substitutes: &S,
+    name: &str,
+    constraint: &Constraint,
+    options: RenderOptions,
+) -> Result<bool, String> {
+    match substitutes.get(name) {
+        Some(value) if !value.is_empty() => constraint.check(&value, options.constraints),
+        _ => Ok(true),
+    }
+}
+
+/// A path template split into literal and placeholder segments.
+///
+/// A placeholder name is the longest run of ASCII letters, digits and `_` following a `:`, so
+/// `:id` and `:id_type` never collide. A `:` that is not followed by a name is kept as a literal.
+/// With [`PlaceholderSyntax::Braces`] placeholders are written as `{id}` instead, or either way
+/// with [`PlaceholderSyntax::Both`].
+///
+/// Substitutes are encoded as a single path segment, so a `/` in them is encoded too. A `.` or
+/// `..` substitute would still make a dot segment, [`Template::render`] writes it as `%2E` or
+/// `%2E%2E` and [`Template::try_render`] rejects it. A splat
+/// placeholder, written `*path` or `{+path}`, encodes each segment of its substitute on its own
+/// and keeps the `/` between them, for object keys and file paths. Substitutes for splats with a
+/// `..` segment are rejected, unless allowed with
+/// [`FormatUrl::allow_dot_segments`](crate::FormatUrl::allow_dot_segments).
+///
+/// A placeholder followed by `=` and a run of unreserved characters, `:version=v2` or
+/// `{version=v2}`, falls back to that default when its substitute is missing or empty. Parts of
+/// the path that only make sense with their substitute can be wrapped in a group starting with a
+/// `/`, as in `/projects(/:project)`, which is left out when a placeholder in it without a
+/// default has no substitute. A single placeholder followed by `?` at the end of a segment,
+/// `/search/:scope?` or `/search/{scope?}`, is short for a group holding it and the `/` before it.
+/// Any other `?` is kept as is, so `/search/:scope?q=1` still starts a query.
+///
+/// Placeholders can say what they accept. `{id:uuid}` refers to a check by name, see
+/// [`Constraints`] for the ones available, and `:id(\d+)` gives a regular expression the whole
+/// substitute has to match. Patterns need the `regex` feature, without it parsing fails rather
+/// than leaving them unchecked. A `(/` right after a name opens a group rather than a pattern,
+/// escape the slash, `:path(\/.+)`, for a pattern starting with one. [`Template::try_render`]
+/// fails with [`FormatUrlError::ConstraintMismatch`] on a substitute that doesn't pass, and with
+/// [`FormatUrlError::MalformedTemplate`] on a constraint name it doesn't know.
+/// [`Template::render`] can't fail, it keeps such a placeholder as written.
+///
+/// A backslash escapes the next `:`, `*`, `{`, `}`, `(`, `)`, `?`, `=` or `\`, so
+/// `/time/12\:30` contains no placeholder. Any other backslash is kept as is.
+///
+/// Parsing happens once, rendering walks the segments in a single pass. Each placeholder is
+/// replaced exactly once and substituted values are never scanned for further placeholders. When
+/// a key appears more than once among the substitutes, the first pair wins.
+///
+/// ```
+/// use format_url::{PlaceholderSyntax, Template};
+///
+/// let template = Template::parse("/user/:id/repos/:repo");
+/// assert_eq!(
+///     template.render(&[("id", "alex"), ("repo", "format-url")]),
+///     "/user/alex/repos/format-url"
+/// );
+///
+/// let template = Template::parse_with("/user/{id}", PlaceholderSyntax::Braces).unwrap();
+/// assert_eq!(template.render(&[("id", "alex")]), "/user/alex");
+///
+/// let template = Template::parse("/files/*path");
+/// assert_eq!(template.render(&[("path", "a b/c.txt")]), "/files/a%20b/c.txt");
+/// assert_eq!(template.render(&[("path", "../secrets")]), "/files/*path");
+///
+/// let template = Template::parse("/:version=v2/orgs/:org/projects(/:project)");
+/// assert_eq!(template.render(&[("org", "acme")]), "/v2/orgs/acme/projects");
+/// assert_eq!(
+///     template.render(&[("org", "acme"), ("project", "x"), ("version", "v3")]),
+///     "/v3/orgs/acme/projects/x"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+    source: String,
+}
+
+impl Template {
+    /// Tokenize a template with `:name` placeholders into its literal and placeholder segments.
+    ///
+    /// # Panics
+    ///
+    /// On a `:name(pattern)` constraint whose pattern isn't a valid regular expression, or on any
+    /// pattern constraint without the `regex` feature. Use [`Template::parse_with`] for templates
+    /// that aren't written in the program.
+    pub fn parse(template: &str) -> Self {
+        Self::parse_with(template, PlaceholderSyntax::Colon)
+            .expect("templates using the colon syntax are only malformed by their patterns")
+    }
+
+    /// Tokenize a template using the given placeholder syntax. Fails on an unclosed `{`, a
+    /// stray `}` or anything but a `?` or default between the name and the `}` when braces are
+    /// recognized, and on a `(pattern)` that can't be compiled or checked.
+    pub fn parse_with(template: &str, syntax: PlaceholderSyntax) -> Result<Self, FormatUrlError> {
+        let mut segments = Segments::default();
+        for token in Tokens::new(template, syntax.colon(), syntax.braces()) {
+            let placeholder = match token {
+                Ok(Token::Literal(c)) => {
+                    segments.literal.push(c);
+                    continue;
+                }
+                Ok(Token::OpenGroup) => {
+                    segments.open_group();
+                    continue;
+                }
+                Ok(Token::CloseGroup) => {
+                    segments.close_group();
+                    continue;
+                }
+                Ok(Token::Placeholder(placeholder)) => placeholder,
+                Err(error) => {
+                    return Err(FormatUrlError::MalformedTemplate {
+                        reason: error.reason.to_string(),
+                        position: error.position,
+                    })
+                }
+            };
+
+            let constraint = match placeholder.constraint {
+                Some(grammar::Constraint::Named(name)) => Some(Constraint::Named(name.to_string())),
+                Some(grammar::Constraint::Pattern(pattern)) => {
+                    let constraint = Constraint::pattern(pattern).map_err(|reason| {
+                        FormatUrlError::MalformedTemplate {
+                            reason,
+                            position: placeholder.position,
+                        }
+                    })?;
+                    Some(constraint)
+                }
+                None => None,
+            };
+            let segment = |default| Segment::Placeholder {
+                name: placeholder.name.to_string(),
+                position: placeholder.position,
+                written: placeholder.written.to_string(),
+                splat: placeholder.splat,
+                default,
+                constraint,
+            };
+            match placeholder.modifier {
+                Modifier::None => segments.push(segment(None)),
+                Modifier::Optional => segments.push_optional(segment(None)),
+                Modifier::Default(default) => segments.push(segment(Some(default.to_string()))),
+            }
+        }
+
+        Ok(Self {
+            segments: segments.finish(),
+            source: template.to_string(),
+        })
+    }
+
+    /// The template as it was originally written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The names of all placeholders in the order they appear.
+    pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+        self.segments.iter().flat_map(Segment::placeholders)
+    }
+
+    /// Render the template, percent-encoding each substitute. Optional groups are left out and
+    /// defaults filled in when substitutes are missing. Other placeholders without a substitute,
+    /// with one that doesn't pass their constraint, and splats whose substitute has a `..`
+    /// segment, are kept as written, which gives a path that looks fine but isn't the one meant.
+    /// Use [`Template::try_render`] for substitutes from outside the program.
+    pub fn render<S: SubstituteSource + ?Sized>(&self, substitutes: &S) -> String {
+        let mut rendered = String::with_capacity(self.source.len());
+        self.write_rendered(
+            substitutes,
+            RenderOptions::new(Some(Component::PathSegment.default_encode_set())),
+            &mut rendered,
+        )
+        .expect("writing to a String can't fail");
+        rendered
+    }
+
+    /// Render the template, failing when a placeholder that isn't optional and has no default has
+    /// no substitute, the substitute is empty or doesn't pass the placeholder's constraint or, for
+    /// a splat, has a `..` segment.
+    pub fn try_render<S: SubstituteSource + ?Sized>(
+        &self,
+        substitutes: &S,
+    ) -> Result<String, FormatUrlError> {
+        self.try_render_with(
+            substitutes,
+            RenderOptions::new(Some(Component::PathSegment.default_encode_set())),
+        )
+    }
+
+    /// Like [`Template::render`], writing to `out` with substitutes written following `options`.
+    pub(crate) fn write_rendered<S, W>(
+        &self,
+        substitutes: &S,
+        options: RenderOptions,
+        out: &mut W,
+    ) -> fmt::Result
+    where
+        S: SubstituteSource + ?Sized,
+        W: fmt::Write,
+    {
+        write_segments(&self.segments, substitutes, options, out)
+    }
+
+    /// Like [`Template::try_render`], with substitutes written following `options`.
+    pub(crate) fn try_render_with<S: SubstituteSource + ?Sized>(
+        &self,
+        substitutes: &S,
+        options: RenderOptions,
+    ) -> Result<String, FormatUrlError> {
+        let mut rendered = String::with_capacity(self.source.len());
+
+        try_render_segments(&self.segments, substitutes, options, &mut rendered)?;
+        Ok(rendered)
+    }
+}
+
+impl FromStr for Template {
+    type Err = std::convert::Infallible;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Ok(Self::parse(template))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use format_url_grammar::path_placeholders;
+
+    use crate::template::Segment;
+    use crate::{FormatUrlError, PlaceholderSyntax, PreEncoded, Template};
+
+    #[test]
+    fn parse_literal_only_test() {
+        let template = Template::parse("/user");
+        assert_eq!(template.placeholders().count(), 0);
+        assert_eq!(template.render(&()), "/user");
+    }
+
+    #[test]
+    fn parse_placeholders_test() {
+        let template = Template::parse("/user/:id/repos/:repo");
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            vec!["id", "repo"]
+        );
+    }
+
+    #[test]
+    fn render_test() {
+        assert_eq!(
+            Template::parse("/user/:id/repos/:repo")
+                .render(&[("repo", "crate"), ("id", "alextes")]),
+            "/user/alextes/repos/crate"
+        );
+    }
+
+    #[test]
+    fn render_missing_substitute_test() {
+        assert_eq!(Template::parse("/user/:id").render(&()), "/user/:id");
+    }
+
+    #[test]
+    fn lone_colon_is_literal_test() {
+        assert_eq!(Template::parse("/a:/b:").render(&()), "/a:/b:");
+    }
+
+    type Case<'a> = (&'a str, &'a [(&'a str, &'a str)], &'a str);
+
+    #[test]
+    fn overlapping_names_test() {
+        let cases: &[Case] = &[
+            (
+                "/org/:id/:id_type",
+                &[("id", "1"), ("id_type", "x")],
+                "/org/1/x",
+            ),
+            (
+                "/org/:id/:id_type",
+                &[("id_type", "x"), ("id", "1")],
+                "/org/1/x",
+            ),
+            (
+                "/org/:id_type/:id",
+                &[("id", "1"), ("id_type", "x")],
+                "/org/x/1",
+            ),
+            ("/org/:id_type", &[("id", "1")], "/org/:id_type"),
+            ("/org/:id", &[("id_type", "x")], "/org/:id"),
+            (
+                "/:a/:ab/:abc",
+                &[("abc", "3"), ("a", "1"), ("ab", "2")],
+                "/1/2/3",
+            ),
+            ("/:a:ab", &[("a", "1"), ("ab", "2")], "/12"),
+            ("/:id.json", &[("id", "1"), ("id.json", "x")], "/1.json"),
+            ("/:id-:id_2", &[("id_2", "2"), ("id", "1")], "/1-2"),
+        ];
+
+        for (template, substitutes, expected) in cases {
+            assert_eq!(
+                Template::parse(template).render(substitutes),
+                *expected,
+                "template {template} with {substitutes:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn substitute_replaced_once_test() {
+        assert_eq!(
+            Template::parse("/:a/:b").render(&[("a", ":b"), ("b", "2")]),
+            "/%3Ab/2"
+        );
+    }
+
+    #[test]
+    fn repeated_placeholder_test() {
+        assert_eq!(Template::parse("/:id/:id").render(&[("id", "1")]), "/1/1");
+    }
+
+    #[test]
+    fn duplicate_substitute_first_wins_test() {
+        assert_eq!(
+            Template::parse("/:id").render(&[("id", "1"), ("id", "2")]),
+            "/1"
+        );
+    }
+
+    #[test]
+    fn try_render_missing_substitute_test() {
+        assert_eq!(
+            Template::parse("/user/:id/repos/:repo").try_render(&[("id", "alextes")]),
+            Err(FormatUrlError::MissingSubstitute {
+                key: "repo".to_string(),
+                position: 16
+            })
+        );
+    }
+
+    #[test]
+    fn try_render_empty_segment_test() {
+        assert_eq!(
+            Template::parse("/user/:id").try_render(&[("id", "")]),
+            Err(FormatUrlError::EmptySegment {
+                key: "id".to_string(),
+                position: 6
+            })
+        );
+    }
+
+    #[test]
+    fn braces_syntax_test() {
+        let template =
+            Template::parse_with("/org/{id}/{id_type}:x", PlaceholderSyntax::Braces).unwrap();
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            vec!["id", "id_type"]
+        );
+        assert_eq!(
+            template.render(&[("id", "1"), ("x", "2")]),
+            "/org/1/{id_type}:x"
+        );
+    }
+
+    #[test]
+    fn both_syntax_test() {
+        let template = Template::parse_with("/org/{org}/:repo", PlaceholderSyntax::Both).unwrap();
+        assert_eq!(template.render(&[("org", "a"), ("repo", "b")]), "/org/a/b");
+    }
+
+    #[test]
+    fn colon_syntax_ignores_braces_test() {
+        assert_eq!(
+            Template::parse("/a/{id}/:id").render(&[("id", "1")]),
+            "/a/{id}/1"
+        );
+    }
+
+    #[test]
+    fn escape_test() {
+        assert_eq!(
+            Template::parse_with(r"/a\:b/\{c\}/\\/:d\e", PlaceholderSyntax::Both)
+                .unwrap()
+                .render(&[("b", "x"), ("c", "y"), ("d", "z")]),
+            r"/a:b/{c}/\/z\e"
+        );
+        assert_eq!(
+            Template::parse(r"/[\:\:1]/:id").render(&[("id", "1")]),
+            "/[::1]/1"
+        );
+    }
+
+    #[test]
+    fn malformed_braces_test() {
+        let malformed =
+            |template: &str| match Template::parse_with(template, PlaceholderSyntax::Braces) {
+                Err(FormatUrlError::MalformedTemplate { position, .. }) => Some(position),
+                _ => None,
+            };
+        assert_eq!(malformed("/user/{id"), Some(6));
+        assert_eq!(malformed("/user/{}"), Some(6));
+        assert_eq!(malformed("/user/{i d}"), Some(6));
+        assert_eq!(malformed("/user/id}"), Some(8));
+        assert_eq!(malformed(r"/user/\{id\}"), None);
+    }
+
+    #[test]
+    fn splat_test() {
+        let template = Template::parse("/files/*path/raw");
+        assert_eq!(template.placeholders().collect::<Vec<_>>(), vec!["path"]);
+        assert_eq!(
+            template.render(&[("path", "a b/c?/d.txt")]),
+            "/files/a%20b/c%3F/d.txt/raw"
+        );
+        assert_eq!(template.render(&()), "/files/*path/raw");
+        assert_eq!(Template::parse(r"/a*/\*b").render(&[("b", "1")]), "/a*/*b");
+    }
+
+    #[test]
+    fn braced_splat_test() {
+        let template = Template::parse_with("/files/{+path}", PlaceholderSyntax::Braces).unwrap();
+        assert_eq!(template.render(&[("path", "a/b")]), "/files/a/b");
+        assert_eq!(template.render(&()), "/files/{+path}");
+        assert!(Template::parse_with("/files/{+}", PlaceholderSyntax::Braces).is_err());
+    }
+
+    #[test]
+    fn splat_dot_segments_test() {
+        let template = Template::parse("/files/*path");
+        assert!(!template.render(&[("path", "a/../b")]).contains(".."));
+        assert_eq!(template.render(&[("path", "a/.../b")]), "/files/a/.../b");
+        assert_eq!(
+            template.try_render(&[("path", "../b")]),
+            Err(FormatUrlError::DotSegment {
+                key: "path".to_string(),
+                position: 7
+            })
+        );
+        for dots in ["%2E%2E", ".%2e", "%2e.", "a/.%2E/../etc"] {
+            assert_eq!(
+                template.try_render(&[("path", PreEncoded(&format!("a/{dots}/b")))]),
+                Err(FormatUrlError::DotSegment {
+                    key: "path".to_string(),
+                    position: 7
+                }),
+                "{dots}"
+            );
+        }
+        assert_eq!(
+            template.try_render(&[("path", PreEncoded("a/%2E%2E%2E/b"))]),
+            Ok("/files/a/%2E%2E%2E/b".to_string())
+        );
+        assert_eq!(
+            template.try_render(&[("path", "")]),
+            Err(FormatUrlError::EmptySegment {
+                key: "path".to_string(),
+                position: 7
+            })
+        );
+    }
+
+    #[test]
+    fn optional_group_test() {
+        let template = Template::parse("/v1/orgs/:org/projects(/:project/:tab)/all");
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            vec!["org", "project", "tab"]
+        );
+        assert_eq!(
+            template.render(&[("org", "a"), ("project", "b"), ("tab", "c")]),
+            "/v1/orgs/a/projects/b/c/all"
+        );
+        assert_eq!(
+            template.render(&[("org", "a"), ("project", "b")]),
+            "/v1/orgs/a/projects/all"
+        );
+        assert_eq!(
+            template.try_render(&[("org", "a"), ("project", "b"), ("tab", "")]),
+            Ok("/v1/orgs/a/projects/all".to_string())
+        );
+        assert_eq!(
+            template.try_render(&[("project", "b")]),
+            Err(FormatUrlError::MissingSubstitute {
+                key: "org".to_string(),
+                position: 9
+            })
+        );
+    }
+
+    #[test]
+    fn group_after_placeholder_test() {
+        let template = Template::parse("/orgs/:org(/:project)");
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            vec!["org", "project"]
+        );
+        assert_eq!(template.render(&[("org", "a")]), "/orgs/a");
+        assert_eq!(
+            template.render(&[("org", "a"), ("project", "b")]),
+            "/orgs/a/b"
+        );
+        assert_eq!(
+            Template::parse("/orgs/:org(/:project)/all").render(&[("org", "a")]),
+            "/orgs/a/all"
+        );
+    }
+
+    #[test]
+    fn optional_placeholder_test() {
+        for syntax in [PlaceholderSyntax::Colon, PlaceholderSyntax::Braces] {
+            let template = match syntax {
+                PlaceholderSyntax::Colon => "/search/:scope?/results/:page?",
+                _ => "/search/{scope?}/results/{page?}",
+            };
+            let template = Template::parse_with(template, syntax).unwrap();
+            assert_eq!(template.render(&()), "/search/results");
+            assert_eq!(
+                template.render(&[("scope", "all"), ("page", "2")]),
+                "/search/all/results/2"
+            );
+            assert_eq!(template.try_render(&()), Ok("/search/results".to_string()));
+        }
+        assert_eq!(Template::parse("/v:version?").render(&()), "/v");
+    }
+
+    #[test]
+    fn question_mark_starting_query_test() {
+        assert_eq!(
+            Template::parse("/search/:scope?q=1").render(&[("scope", "a")]),
+            "/search/a?q=1"
+        );
+        assert_eq!(
+            Template::parse(r"/search/:scope\?").render(&[("scope", "a")]),
+            "/search/a?"
+        );
+    }
+
+    #[test]
+    fn default_test() {
+        let template = Template::parse("/:version=v2.1/users/:id=me/:tab=");
+        assert_eq!(template.render(&[("tab", "x")]), "/v2.1/users/me/x=");
+        assert_eq!(
+            template.render(&[("version", "v3"), ("id", ""), ("tab", "x")]),
+            "/v3/users/me/x="
+        );
+        assert_eq!(
+            Template::parse_with("/{version=v2}/{id}", PlaceholderSyntax::Braces)
+                .unwrap()
+                .try_render(&[("id", "1")]),
+            Ok("/v2/1".to_string())
+        );
+        assert!(Template::parse_with("/{version=}", PlaceholderSyntax::Braces).is_err());
+        assert!(Template::parse_with("/{version=a/b}", PlaceholderSyntax::Braces).is_err());
+        assert_eq!(Template::parse(r"/:a\=b").render(&[("a", "1")]), "/1=b");
+    }
+
+    #[test]
+    fn parentheses_outside_groups_test() {
+        let cases: &[Case] = &[
+            ("/Products(:id)", &[("id", "1")], "/Products(1)"),
+            ("/a(/:id", &[], "/a(/:id"),
+            ("/a/:id)", &[("id", "1")], "/a/1)"),
+            (r"/a\(/:id\)", &[], "/a(/:id)"),
+        ];
+
+        for (template, substitutes, expected) in cases {
+            assert_eq!(
+                Template::parse(template).render(substitutes),
+                *expected,
+                "template {template} with {substitutes:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn named_constraint_test() {
+        let template = Template::parse_with(
+            "/users/{id:uuid}/posts/{slug:slug?}",
+            PlaceholderSyntax::Braces,
+        )
+        .unwrap();
+        assert_eq!(
+            template.placeholders().collect::<Vec<_>>(),
+            vec!["id", "slug"]
+        );
+        let id = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(
+            template.try_render(&[("id", id), ("slug", "hello-world")]),
+            Ok(format!("/users/{id}/posts/hello-world"))
+        );
+        assert_eq!(
+            template.try_render(&[("id", id)]),
+            Ok(format!("/users/{id}/posts"))
+        );
+        assert_eq!(
+            template.try_render(&[("id", "42")]),
+            Err(FormatUrlError::ConstraintMismatch {
+                key: "id".to_string(),
+                constraint: "uuid".to_string(),
+                position: 7
+            })
+        );
+        assert!(!template
+            .render(&[("id", "42"), ("slug", "Hello World")])
+            .contains("42"));
+        assert!(matches!(
+            Template::parse_with("/{id:sku}", PlaceholderSyntax::Braces)
+                .unwrap()
+                .try_render(&[("id", "1")]),
+            Err(FormatUrlError::MalformedTemplate { position: 1, .. })
+        ));
+        assert!(Template::parse_with("/{id:}", PlaceholderSyntax::Braces).is_err());
+        assert!(Template::parse_with("/{id:a-b}", PlaceholderSyntax::Braces).is_err());
+    }
+
+    #[test]
+    fn pattern_constraint_test() {
+        assert_eq!(Template::parse("/a(:id").render(&[("id", "1")]), "/a(1");
+        assert_eq!(Template::parse("/:id(x").render(&[("id", "1")]), "/1(x");
+
+        let template =
+            Template::parse_with(r"/items/:id(\d+(\.\d+)?)=0/:rest", PlaceholderSyntax::Colon);
+        #[cfg(feature = "regex")]
+        {
+            let template = template.unwrap();
+            assert_eq!(
+                template.placeholders().collect::<Vec<_>>(),
+                vec!["id", "rest"]
+            );
+            assert_eq!(template.render(&[("rest", "x")]), "/items/0/x");
+            assert_eq!(
+                template.try_render(&[("id", "1.5"), ("rest", "x")]),
+                Ok("/items/1.5/x".to_string())
+            );
+            assert_eq!(
+                template.try_render(&[("id", "1.5.0"), ("rest", "x")]),
+                Err(FormatUrlError::ConstraintMismatch {
+                    key: "id".to_string(),
+                    constraint: r"(\d+(\.\d+)?)".to_string(),
+                    position: 7
+                })
+            );
+            assert!(!template
+                .render(&[("id", "a"), ("rest", "x")])
+                .contains("/a/"));
+            assert!(matches!(
+                Template::parse_with("/:id([)", PlaceholderSyntax::Colon),
+                Err(FormatUrlError::MalformedTemplate { position: 1, .. })
+            ));
+            let template = Template::parse(r"/files*path(\/.+)");
+            assert_eq!(
+                template.try_render(&[("path", "/a/b")]),
+                Ok("/files/a/b".to_string())
+            );
+            assert!(template.try_render(&[("path", "a/b")]).is_err());
+        }
+        #[cfg(not(feature = "regex"))]
+        assert!(matches!(
+            template,
+            Err(FormatUrlError::MalformedTemplate { position: 7, .. })
+        ));
+    }
+
+    #[test]
+    fn render_twice_test() {
+        let template = Template::parse("/user/:id");
+        assert_eq!(template.render(&[("id", "a")]), "/user/a");
+        assert_eq!(template.render(&[("id", "b")]), "/user/b");
+    }
+
+    /// The placeholders as the `Endpoint` derive sees them should be the ones a `Template` has,
+    /// optional where the template can leave them out.
+    #[test]
+    fn derive_placeholders_test() {
+        fn optional(segments: &[Segment], in_group: bool, out: &mut Vec<(String, bool)>) {
+            for segment in segments {
+                match segment {
+                    Segment::Literal(_) => {}
+                    Segment::Placeholder { name, default, .. } => {
+                        out.push((name.clone(), in_group || default.is_some()))
+                    }
+                    Segment::Group(group) => optional(group, true, out),
+                }
+            }
+        }
+
+        for source in [
+            "/repos/:owner/:repo_id/time/12\\:30/:",
+            "/buckets/:bucket/*key/\\*raw/*",
+            r"/items/:id(\d+(:x)?)/a\(:b\)/:c\?\=d",
+            "/orgs/:org(/:project/:tab)/:page?/:sort=asc/:q?x=1",
+            r"/:id(\d+)?/:x(/y)",
+            "/v1/orgs/:org/projects(/:project/:tab)/all",
+            "/:version=v2.1/users/:id=me/:tab=",
+            "/a/{id}/:id(/:b)/(/c/:d/e)/:f?/",
+            r"/files*path(\/.+)/(/:x\)",
+        ] {
+            let template = match Template::parse_with(source, PlaceholderSyntax::Colon) {
+                Ok(template) => template,
+                Err(_) if !cfg!(feature = "regex") => continue,
+                Err(error) => panic!("{source}: {error}"),
+            };
+            let mut expected = Vec::new();
+            optional(&template.segments, false, &mut expected);
+            let derived = path_placeholders(source)
+                .into_iter()
+                .map(|placeholder| (placeholder.name.to_string(), placeholder.optional))
+                .collect::<Vec<_>>();
+            assert_eq!(derived, expected, "{source}");
+        }
+    }
+}
@@ -0,0 +1,670 @@
+//! [RFC 6570](https://www.rfc-editor.org/rfc/rfc6570) URI Templates, up to and including level 4.
+
+use std::borrow::Cow;
+use std::fmt::{self, Write};
+
+use percent_encoding::{utf8_percent_encode, AsciiSet};
+
+use crate::encode::UNRESERVED;
+use crate::{FormatUrlError, SubstituteSource};
+
+/// Everything but the unreserved and reserved characters.
+const UNRESERVED_RESERVED: &AsciiSet = &UNRESERVED
+    .remove(b':')
+    .remove(b'/')
+    .remove(b'?')
+    .remove(b'#')
+    .remove(b'[')
+    .remove(b']')
+    .remove(b'@')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=');
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Simple,
+    Reserved,
+    Fragment,
+    Label,
+    Path,
+    PathParam,
+    Query,
+    QueryContinuation,
+}
+
+impl Operator {
+    fn from_char(c: char) -> Option<Self> {
+        match c {
+            '+' => Some(Operator::Reserved),
+            '#' => Some(Operator::Fragment),
+            '.' => Some(Operator::Label),
+            '/' => Some(Operator::Path),
+            ';' => Some(Operator::PathParam),
+            '?' => Some(Operator::Query),
+            '&' => Some(Operator::QueryContinuation),
+            _ => None,
+        }
+    }
+
+    fn first(self) -> &'static str {
+        match self {
+            Operator::Simple | Operator::Reserved => "",
+            Operator::Fragment => "#",
+            Operator::Label => ".",
+            Operator::Path => "/",
+            Operator::PathParam => ";",
+            Operator::Query => "?",
+            Operator::QueryContinuation => "&",
+        }
+    }
+
+    fn separator(self) -> &'static str {
+        match self {
+            Operator::Simple | Operator::Reserved | Operator::Fragment => ",",
+            Operator::Label => ".",
+            Operator::Path => "/",
+            Operator::PathParam => ";",
+            Operator::Query | Operator::QueryContinuation => "&",
+        }
+    }
+
+    fn named(self) -> bool {
+        matches!(
+            self,
+            Operator::PathParam | Operator::Query | Operator::QueryContinuation
+        )
+    }
+
+    fn if_empty(self) -> &'static str {
+        match self {
+            Operator::Query | Operator::QueryContinuation => "=",
+            _ => "",
+        }
+    }
+
+    fn allows_reserved(self) -> bool {
+        matches!(self, Operator::Reserved | Operator::Fragment)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Modifier {
+    None,
+    Prefix(usize),
+    Explode,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct VarSpec {
+    name: String,
+    modifier: Modifier,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression {
+        operator: Operator,
+        varspecs: Vec<VarSpec>,
+    },
+}
+
+/// The value of a URI Template variable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateValue {
+    String(String),
+    List(Vec<String>),
+    AssocList(Vec<(String, String)>),
+}
+
+impl From<&str> for TemplateValue {
+    fn from(value: &str) -> Self {
+        TemplateValue::String(value.to_string())
+    }
+}
+
+impl From<String> for TemplateValue {
+    fn from(value: String) -> Self {
+        TemplateValue::String(value)
+    }
+}
+
+impl From<Vec<&str>> for TemplateValue {
+    fn from(values: Vec<&str>) -> Self {
+        TemplateValue::List(values.into_iter().map(str::to_string).collect())
+    }
+}
+
+impl From<Vec<String>> for TemplateValue {
+    fn from(values: Vec<String>) -> Self {
+        TemplateValue::List(values)
+    }
+}
+
+impl From<Vec<(&str, &str)>> for TemplateValue {
+    fn from(pairs: Vec<(&str, &str)>) -> Self {
+        TemplateValue::AssocList(
+            pairs
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        )
+    }
+}
+
+impl From<Vec<(String, String)>> for TemplateValue {
+    fn from(pairs: Vec<(String, String)>) -> Self {
+        TemplateValue::AssocList(pairs)
+    }
+}
+
+/// A borrowed view of a variable value, so plain string substitutes don't need to be copied.
+enum Value<'v> {
+    String(Cow<'v, str>),
+    List(&'v [String]),
+    AssocList(&'v [(String, String)]),
+}
+
+impl<'v> From<&'v TemplateValue> for Value<'v> {
+    fn from(value: &'v TemplateValue) -> Self {
+        match value {
+            TemplateValue::String(value) => Value::String(Cow::Borrowed(value)),
+            TemplateValue::List(values) => Value::List(values),
+            TemplateValue::AssocList(pairs) => Value::AssocList(pairs),
+        }
+    }
+}
+
+fn is_hex_triplet(bytes: &[u8]) -> bool {
+    bytes.len() >= 3
+        && bytes[0] == b'%'
+        && bytes[1].is_ascii_hexdigit()
+        && bytes[2].is_ascii_hexdigit()
+}
+
+/// Encode everything outside of unreserved and reserved characters, keeping existing `%XX`
+/// triplets intact.
+fn encode_reserved<W: Write + ?Sized>(value: &str, out: &mut W) -> fmt::Result {
+    let mut rest = value;
+    while let Some(index) = rest.find('%') {
+        write!(
+            out,
+            "{}",
+            utf8_percent_encode(&rest[..index], UNRESERVED_RESERVED)
+        )?;
+        if is_hex_triplet(&rest.as_bytes()[index..]) {
+            out.write_str(&rest[index..index + 3])?;
+            rest = &rest[index + 3..];
+        } else {
+            out.write_str("%25")?;
+            rest = &rest[index + 1..];
+        }
+    }
+    write!(out, "{}", utf8_percent_encode(rest, UNRESERVED_RESERVED))
+}
+
+fn encode<W: Write + ?Sized>(value: &str, allow_reserved: bool, out: &mut W) -> fmt::Result {
+    if allow_reserved {
+        encode_reserved(value, out)
+    } else {
+        write!(out, "{}", utf8_percent_encode(value, UNRESERVED))
+    }
+}
+
+fn is_varchar(bytes: &[u8], index: usize) -> usize {
+    match bytes[index] {
+        b if b.is_ascii_alphanumeric() || b == b'_' => 1,
+        b'%' if is_hex_triplet(&bytes[index..]) => 3,
+        _ => 0,
+    }
+}
+
+fn parse_varname(name: &str) -> bool {
+    let bytes = name.as_bytes();
+    let mut index = 0;
+    let mut after_dot = true;
+    while index < bytes.len() {
+        if bytes[index] == b'.' && !after_dot {
+            after_dot = true;
+            index += 1;
+            continue;
+        }
+        match is_varchar(bytes, index) {
+            0 => return false,
+            len => index += len,
+        }
+        after_dot = false;
+    }
+    !after_dot
+}
+
+fn parse_varspec(varspec: &str, position: usize) -> Result<VarSpec, FormatUrlError> {
+    let malformed = |reason: &str| FormatUrlError::MalformedTemplate {
+        reason: reason.to_string(),
+        position,
+    };
+
+    let (name, modifier) = if let Some(name) = varspec.strip_suffix('*') {
+        (name, Modifier::Explode)
+    } else if let Some((name, length)) = varspec.split_once(':') {
+        let length = Some(length)
+            .filter(|length| {
+                (1..=4).contains(&length.len())
+                    && !length.starts_with('0')
+                    && length.bytes().all(|b| b.is_ascii_digit())
+            })
+            .and_then(|length| length.parse().ok())
+            .ok_or_else(|| malformed("prefix length must be between 1 and 9999"))?;
+        (name, Modifier::Prefix(length))
+    } else {
+        (varspec, Modifier::None)
+    };
+
+    if !parse_varname(name) {
+        return Err(malformed("invalid variable name"));
+    }
+
+    Ok(VarSpec {
+        name: name.to_string(),
+        modifier,
+    })
+}
+
+fn parse_expression(expression: &str, position: usize) -> Result<Part, FormatUrlError> {
+    let mut chars = expression.chars();
+    let (operator, varlist) = match chars.next() {
+        Some(c) if "=,!@|".contains(c) => {
+            return Err(FormatUrlError::MalformedTemplate {
+                reason: format!("operator {c:?} is reserved"),
+                position,
+            })
+        }
+        Some(c) => match Operator::from_char(c) {
+            Some(operator) => (operator, chars.as_str()),
+            None => (Operator::Simple, expression),
+        },
+        None => {
+            return Err(FormatUrlError::MalformedTemplate {
+                reason: "empty expression".to_string(),
+                position,
+            })
+        }
+    };
+
+    let varspecs = varlist
+        .split(',')
+        .map(|varspec| parse_varspec(varspec, position))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Part::Expression { operator, varspecs })
+}
+
+/// An [RFC 6570](https://www.rfc-editor.org/rfc/rfc6570) URI Template such as
+/// `/repos{/owner,repo}{?page,per_page}`.
+///
+/// All operators (`+`, `#`, `.`, `/`, `;`, `?` and `&`) and both the explode (`*`) and prefix
+/// (`:n`) modifiers are supported. Variables that are not given are undefined and, as the RFC
+/// prescribes, expand to nothing.
+///
+/// ```
+/// use format_url::UriTemplate;
+///
+/// let template = UriTemplate::parse("/repos{/owner,repo}{?page,per_page}").unwrap();
+/// assert_eq!(
+///     template.expand(&[("owner", "alextes".into()), ("repo", "format-url".into()), ("page", "2".into())]),
+///     "/repos/alextes/format-url?page=2"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UriTemplate {
+    parts: Vec<Part>,
+    source: String,
+}
+
+impl UriTemplate {
+    /// Parse a URI Template, failing on unclosed or invalid expressions.
+    pub fn parse(template: &str) -> Result<Self, FormatUrlError> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        let mut offset = 0;
+
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                return Err(FormatUrlError::MalformedTemplate {
+                    reason: "unmatched '}'".to_string(),
+                    position: offset + start,
+                });
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .ok_or_else(|| FormatUrlError::MalformedTemplate {
+                    reason: "unclosed '{'".to_string(),
+                    position: offset + start,
+                })?;
+
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            parts.push(parse_expression(&rest[start + 1..end], offset + start)?);
+
+            offset += end + 1;
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+
+        Ok(Self {
+            parts,
+            source: template.to_string(),
+        })
+    }
+
+    /// The template as it was originally written.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// The names of all variables in the order they appear.
+    pub fn variables(&self) -> impl Iterator<Item = &str> {
+        self.parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Expression { varspecs, .. } => Some(varspecs),
+                Part::Literal(_) => None,
+            })
+            .flatten()
+            .map(|varspec| varspec.name.as_str())
+    }
+
+    /// Expand the template using the given variables.
+    pub fn expand(&self, variables: &[(&str, TemplateValue)]) -> String {
+        self.expand_with(|name| {
+            variables
+                .iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.into())
+        })
+    }
+
+    /// Expand the template like [`expand`](Self::expand), but fail with
+    /// [`FormatUrlError::InvalidSubstitute`] when a prefix modifier such as `{keys:1}` is given a
+    /// list or associative array, which the RFC doesn't allow.
+    pub fn try_expand(
+        &self,
+        variables: &[(&str, TemplateValue)],
+    ) -> Result<String, FormatUrlError> {
+        let prefixed_composite = self
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                Part::Expression { varspecs, .. } => Some(varspecs),
+                Part::Literal(_) => None,
+            })
+            .flatten()
+            .filter(|varspec| matches!(varspec.modifier, Modifier::Prefix(_)))
+            .find(|varspec| {
+                variables.iter().any(|(key, value)| {
+                    *key == varspec.name && !matches!(value, TemplateValue::String(_))
+                })
+            });
+        if let Some(varspec) = prefixed_composite {
+            return Err(FormatUrlError::InvalidSubstitute {
+                key: varspec.name.clone(),
+                reason: "a prefix modifier only applies to strings".to_string(),
+            });
+        }
+        Ok(self.expand(variables))
+    }
+
+    /// Expand the template using plain string substitutes.
+    pub(crate) fn expand_strings<S: SubstituteSource + ?Sized>(&self, substitutes: &S) -> String {
+        let mut expanded = String::with_capacity(self.source.len());
+        self.write_strings(substitutes, &mut expanded)
+            .expect("writing to a String can't fail");
+        expanded
+    }
+
+    /// Expand the template using plain string substitutes into `out`.
+    pub(crate) fn write_strings<S, W>(&self, substitutes: &S, out: &mut W) -> fmt::Result
+    where
+        S: SubstituteSource + ?Sized,
+        W: Write + ?Sized,
+    {
+        self.write_with(|name| substitutes.get(name).map(Value::String), out)
+    }
+
+    /// Whether expanding the template only ever gives a path: it has no `?` or `#` of its own and
+    /// no expressions that can write one.
+    pub(crate) fn stays_in_path(&self) -> bool {
+        self.parts.iter().all(|part| match part {
+            Part::Literal(literal) => !literal.contains(['?', '#']),
+            Part::Expression { operator, .. } => !matches!(
+                operator,
+                Operator::Reserved
+                    | Operator::Fragment
+                    | Operator::Query
+                    | Operator::QueryContinuation
+            ),
+        })
+    }
+
+    fn expand_with<'v>(&self, lookup: impl Fn(&str) -> Option<Value<'v>>) -> String {
+        let mut expanded = String::with_capacity(self.source.len());
+        self.write_with(lookup, &mut expanded)
+            .expect("writing to a String can't fail");
+        expanded
+    }
+
+    fn write_with<'v, W: Write + ?Sized>(
+        &self,
+        lookup: impl Fn(&str) -> Option<Value<'v>>,
+        out: &mut W,
+    ) -> fmt::Result {
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => encode_reserved(literal, out)?,
+                Part::Expression { operator, varspecs } => {
+                    let mut first = true;
+                    for varspec in varspecs {
+                        if let Some(value) = lookup(&varspec.name) {
+                            expand_varspec(*operator, varspec, value, &mut first, out)?;
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn expand_varspec<W: Write + ?Sized>(
+    operator: Operator,
+    varspec: &VarSpec,
+    value: Value,
+    first: &mut bool,
+    out: &mut W,
+) -> fmt::Result {
+    let is_empty_composite = match value {
+        Value::String(_) => false,
+        Value::List(values) => values.is_empty(),
+        Value::AssocList(pairs) => pairs.is_empty(),
+    };
+    if is_empty_composite {
+        return Ok(());
+    }
+
+    out.write_str(if *first {
+        operator.first()
+    } else {
+        operator.separator()
+    })?;
+    *first = false;
+
+    let allow_reserved = operator.allows_reserved();
+    let name = varspec.name.as_str();
+
+    match (value, varspec.modifier) {
+        (Value::String(value), modifier) => {
+            let value: &str = &value;
+            if operator.named() {
+                out.write_str(name)?;
+                if value.is_empty() {
+                    out.write_str(operator.if_empty())?;
+                    return Ok(());
+                }
+                out.write_char('=')?;
+            }
+            let value = match modifier {
+                Modifier::Prefix(length) => value
+                    .char_indices()
+                    .nth(length)
+                    .map_or(value, |(index, _)| &value[..index]),
+                Modifier::None | Modifier::Explode => value,
+            };
+            encode(value, allow_reserved, out)?;
+        }
+        (Value::List(values), Modifier::Explode) => {
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.write_str(operator.separator())?;
+                }
+                if operator.named() {
+                    out.write_str(name)?;
+                    if value.is_empty() {
+                        out.write_str(operator.if_empty())?;
+                        continue;
+                    }
+                    out.write_char('=')?;
+                }
+                encode(value, allow_reserved, out)?;
+            }
+        }
+        (Value::AssocList(pairs), Modifier::Explode) => {
+            for (index, (key, value)) in pairs.iter().enumerate() {
+                if index > 0 {
+                    out.write_str(operator.separator())?;
+                }
+                encode(key, allow_reserved, out)?;
+                if value.is_empty() && operator.named() {
+                    out.write_str(operator.if_empty())?;
+                    continue;
+                }
+                out.write_char('=')?;
+                encode(value, allow_reserved, out)?;
+            }
+        }
+        (Value::List(values), _) => {
+            if operator.named() {
+                out.write_str(name)?;
+                out.write_char('=')?;
+            }
+            for (index, value) in values.iter().enumerate() {
+                if index > 0 {
+                    out.write_char(',')?;
+                }
+                encode(value, allow_reserved, out)?;
+            }
+        }
+        (Value::AssocList(pairs), _) => {
+            if operator.named() {
+                out.write_str(name)?;
+                out.write_char('=')?;
+            }
+            for (index, (key, value)) in pairs.iter().enumerate() {
+                if index > 0 {
+                    out.write_char(',')?;
+                }
+                encode(key, allow_reserved, out)?;
+                out.write_char(',')?;
+                encode(value, allow_reserved, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{FormatUrlError, TemplateValue, UriTemplate};
+
+    #[test]
+    fn simple_expansion_test() {
+        let template = UriTemplate::parse("/user/{id}").unwrap();
+        assert_eq!(
+            template.expand(&[("id", "alex tes".into())]),
+            "/user/alex%20tes"
+        );
+    }
+
+    #[test]
+    fn undefined_variables_test() {
+        let template = UriTemplate::parse("/search{?q,page}").unwrap();
+        assert_eq!(template.expand(&[("page", "2".into())]), "/search?page=2");
+        assert_eq!(template.expand(&[]), "/search");
+    }
+
+    #[test]
+    fn list_and_assoc_test() {
+        let template = UriTemplate::parse("{/ids*}{?filter*}").unwrap();
+        assert_eq!(
+            template.expand(&[
+                ("ids", vec!["1", "2"].into()),
+                ("filter", TemplateValue::from(vec![("state", "open")])),
+            ]),
+            "/1/2?state=open"
+        );
+    }
+
+    #[test]
+    fn try_expand_test() {
+        let template = UriTemplate::parse("{var:3}{?keys:1}").unwrap();
+        assert_eq!(
+            template.try_expand(&[("var", "value".into())]),
+            Ok("val".to_string())
+        );
+        assert_eq!(
+            template.try_expand(&[("keys", vec![("semi", ";")].into())]),
+            Err(FormatUrlError::InvalidSubstitute {
+                key: "keys".to_string(),
+                reason: "a prefix modifier only applies to strings".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn variables_test() {
+        let template = UriTemplate::parse("/repos{/owner,repo}{?page}").unwrap();
+        assert_eq!(
+            template.variables().collect::<Vec<_>>(),
+            vec!["owner", "repo", "page"]
+        );
+    }
+
+    #[test]
+    fn malformed_test() {
+        let malformed = |template: &str| match UriTemplate::parse(template) {
+            Err(FormatUrlError::MalformedTemplate { position, .. }) => Some(position),
+            _ => None,
+        };
+        assert_eq!(malformed("/user/{id"), Some(6));
+        assert_eq!(malformed("/user/id}"), Some(8));
+        assert_eq!(malformed("/user/{}"), Some(6));
+        assert_eq!(malformed("/user/{=id}"), Some(6));
+        assert_eq!(malformed("/user/{id:0}"), Some(6));
+        assert_eq!(malformed("/user/{id:10000}"), Some(6));
+        assert_eq!(malformed("/user/{i d}"), Some(6));
+        assert_eq!(malformed("/user/{id.}"), Some(6));
+    }
+}
@@ -0,0 +1,342 @@
+//! Rendering a [`FormatUrl`] straight into a writer.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::encode::{write_encoded, AppendPath, TrimStart};
+use crate::query::write_query;
+use crate::template::{RenderOptions, Template};
+use crate::{Component, DuplicateKeys, FormatUrl, JoinMode, PathTemplate, UriTemplate};
+
+/// A path or fragment template that can be written front to back.
+enum Streamable<'t> {
+    Parsed(Cow<'t, Template>),
+    Uri(&'t UriTemplate),
+}
+
+impl Streamable<'_> {
+    fn write<W: fmt::Write>(
+        &self,
+        url: &FormatUrl<'_>,
+        options: RenderOptions,
+        out: &mut W,
+    ) -> fmt::Result {
+        match self {
+            Streamable::Parsed(template) => {
+                template.write_rendered(&url.substitutes(), options, out)
+            }
+            Streamable::Uri(template) => template.write_strings(&url.substitutes(), out),
+        }
+    }
+}
+
+impl<'a> FormatUrl<'a> {
+    /// Write the URL [`FormatUrl::format_url`] would give to `out`. Substitutes and query
+    /// parameters are percent-encoded on the way and templates given as `&str` are parsed once,
+    /// when they're set, so most configurations write without allocating. These do allocate:
+    ///
+    /// * The URL is formatted into a `String` first when the whole path or query is needed at
+    ///   once, which is the case for:
+    ///   - [`JoinMode::Resolve`], which resolves the path against the base.
+    ///   - A query in the base along with query parameters, unless the keys are kept with
+    ///     [`DuplicateKeys::KeepBoth`].
+    ///   - Path and fragment templates with a `?` or `#` in them, which may start a query or
+    ///     fragment of their own. For URI Templates that's also any `{+var}`, `{#var}`, `{?var}`
+    ///     or `{&var}` expression.
+    ///   - Malformed templates, which are also parsed again on every call.
+    /// * Splat placeholders, defaults, optional groups, constraints and
+    ///   [`DoubleEncoding::Normalize`](crate::DoubleEncoding::Normalize) read the substitutes they
+    ///   look at into a `String`. `Normalize` copies the query parameters as well.
+    ///
+    /// Setting the substitutes boxes them, once. To write to an [`std::io::Write`], go through the
+    /// [`fmt::Display`] implementation with `write!(out, "{url}")`.
+    ///
+    /// ```
+    /// use format_url::FormatUrl;
+    ///
+    /// let mut out = String::from("GET ");
+    /// FormatUrl::new("https://api.example.com/")
+    ///     .with_path_template("/user/:name")
+    ///     .with_substitutes(vec![("name", "alex")])
+    ///     .write_url(&mut out)
+    ///     .unwrap();
+    /// assert_eq!(out, "GET https://api.example.com/user/alex");
+    /// ```
+    pub fn write_url<W: fmt::Write + ?Sized>(&self, out: &mut W) -> fmt::Result {
+        let Some((path, fragment)) = self.streamable_templates() else {
+            return out.write_str(&self.format_joined());
+        };
+
+        let base = self.base.without_query();
+        let options = self.render_options(Component::PathSegment);
+        match &path {
+            Some(path) if self.join_mode == JoinMode::PrefixAppend => {
+                let trimmed_base = base.trim_end_matches('/');
+                self.write_base(out, trimmed_base)?;
+                let mut path_out = AppendPath::new(out, &base[trimmed_base.len()..]);
+                path.write(self, options, &mut path_out)?;
+                path_out.finish()?;
+            }
+            Some(path) => {
+                self.write_base(out, base)?;
+                let mut path_out = TrimStart::new(out, '/', usize::from(base.ends_with('/')));
+                path.write(self, options, &mut path_out)?;
+            }
+            None => self.write_base(out, base)?,
+        }
+
+        let base_query = self.base.query().unwrap_or("");
+        let separator = if base_query.is_empty() {
+            "?"
+        } else {
+            out.write_char('?')?;
+            out.write_str(base_query)?;
+            "&"
+        };
+        if let Some(query_params) = self.query_params() {
+            let (key_set, value_set) = self.query_encode_sets();
+            write_query(
+                out,
+                &query_params,
+                key_set,
+                value_set,
+                self.array_format,
+                separator,
+            )?;
+        }
+
+        match (&fragment, self.base.fragment()) {
+            (Some(fragment), _) => {
+                out.write_char('#')?;
+                fragment.write(
+                    self,
+                    self.render_options(Component::Fragment),
+                    &mut TrimStart::new(out, '#', 1),
+                )
+            }
+            (None, Some(base_fragment)) => {
+                out.write_char('#')?;
+                out.write_str(base_fragment.strip_prefix('#').unwrap_or(base_fragment))
+            }
+            (None, None) => Ok(()),
+        }
+    }
+
+    /// Write `base`, the start of the base URL, with the user info given to
+    /// [`FormatUrl::with_userinfo`] in place of its own.
+    pub(crate) fn write_base<W: fmt::Write + ?Sized>(
+        &self,
+        out: &mut W,
+        base: &str,
+    ) -> fmt::Result {
+        let (Some((user, password)), Some(userinfo_range)) =
+            (self.userinfo, self.base.userinfo_range())
+        else {
+            return out.write_str(base);
+        };
+        if userinfo_range.end > base.len() {
+            return out.write_str(base);
+        }
+
+        out.write_str(&base[..userinfo_range.start])?;
+        self.write_userinfo(out, user, password)?;
+        out.write_str(&base[userinfo_range.end..])
+    }
+
+    /// The authority of the base URL, with the user info given to [`FormatUrl::with_userinfo`]
+    /// in place of its own.
+    pub(crate) fn authority(&self) -> Option<Cow<'a, str>> {
+        let authority = self.base.authority()?;
+        let Some((user, password)) = self.userinfo else {
+            return Some(Cow::Borrowed(authority));
+        };
+        let host_port = authority
+            .rsplit_once('@')
+            .map_or(authority, |(_, host_port)| host_port);
+        let mut out = String::new();
+        self.write_userinfo(&mut out, user, password)
+            .expect("writing to a String can't fail");
+        out.push_str(host_port);
+        Some(Cow::Owned(out))
+    }
+
+    /// Write `user` and `password`, encoded, followed by an `@`.
+    fn write_userinfo<W: fmt::Write + ?Sized>(
+        &self,
+        out: &mut W,
+        user: &str,
+        password: Option<&str>,
+    ) -> fmt::Result {
+        let encode_set = self.encode_sets.get(Component::Userinfo);
+        write_encoded(out, user, encode_set)?;
+        if let Some(password) = password {
+            out.write_char(':')?;
+            write_encoded(out, password, encode_set)?;
+        }
+        out.write_char('@')
+    }
+
+    /// The parsed path and fragment templates, when they and the rest of the configuration allow
+    /// writing the URL front to back in one go.
+    fn streamable_templates(&self) -> Option<(Option<Streamable<'_>>, Option<Streamable<'_>>)> {
+        let path = match &self.path_template {
+            Some(path_template) => Some(self.streamable_template(path_template)?),
+            None => None,
+        };
+        let fragment = match &self.fragment_template {
+            Some(fragment_template) => Some(self.streamable_template(fragment_template)?),
+            None => None,
+        };
+
+        let merges_keys = self.duplicate_keys != DuplicateKeys::KeepBoth
+            && !self.base.query().unwrap_or("").is_empty()
+            && self
+                .query_params
+                .as_ref()
+                .is_some_and(|params| !params.is_empty());
+        if self.join_mode == JoinMode::Resolve || merges_keys {
+            return None;
+        }
+
+        Some((path, fragment))
+    }
+
+    /// A well-formed template that can't start a query or fragment of its own.
+    fn streamable_template<'t>(&self, template: &'t PathTemplate) -> Option<Streamable<'t>> {
+        match template {
+            PathTemplate::Uri(template) if template.stays_in_path() => {
+                Some(Streamable::Uri(template))
+            }
+            PathTemplate::Uri(_) => None,
+            _ if template.as_written().contains(['?', '#']) => None,
+            _ => template
+                .parse(self.placeholder_syntax)
+                .ok()
+                .map(Streamable::Parsed),
+        }
+    }
+}
+
+impl fmt::Display for FormatUrl<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_url(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DuplicateKeys, FormatUrl, JoinMode, PlaceholderSyntax, Template, UriTemplate};
+
+    fn configurations() -> Vec<FormatUrl<'static>> {
+        let mut urls = Vec::new();
+        for base in [
+            "https://x.com",
+            "https://x.com/api/",
+            "https://x.com/api?key=k",
+            "https://x.com/api/?#top",
+        ] {
+            for join_mode in [JoinMode::Concat, JoinMode::PrefixAppend, JoinMode::Resolve] {
+                for path in [
+                    "",
+                    "/",
+                    "(/:missing)",
+                    "//user/:id",
+                    "user/:id",
+                    ":id/x",
+                    "/a?b=:id",
+                    "../:id",
+                    "/files/*id",
+                    "(/:missing)/:id",
+                    "/:id=x/:missing?",
+                    "/:id(\\d+)/:missing(x)?",
+                ] {
+                    urls.push(
+                        FormatUrl::new(base)
+                            .with_join_mode(join_mode)
+                            .with_path_template(path)
+                            .with_substitutes(vec![("id", "a b/c")]),
+                    );
+                }
+                for path in ["/user{/id}", "{id}/x", "{/missing}", "/user/{+id}"] {
+                    urls.push(
+                        FormatUrl::new(base)
+                            .with_join_mode(join_mode)
+                            .with_path_template(UriTemplate::parse(path).unwrap())
+                            .with_substitutes(vec![("id", "a b/c")]),
+                    );
+                }
+                urls.push(
+                    FormatUrl::new(base)
+                        .with_join_mode(join_mode)
+                        .with_path_template("/user/:id")
+                        .with_fragment("#:id")
+                        .with_query_params(vec![("key", "v&w"), ("page", "2")]),
+                );
+            }
+        }
+        urls.push(
+            FormatUrl::new("https://x.com?key=k")
+                .with_duplicate_keys(DuplicateKeys::Override)
+                .with_query_params(vec![("key", "v")]),
+        );
+        urls.push(
+            FormatUrl::new("https://x.com/")
+                .with_placeholder_syntax(PlaceholderSyntax::Braces)
+                .with_path_template("/user/{id"),
+        );
+        urls.push(
+            FormatUrl::new("https://x.com")
+                .with_path_template(Template::parse("/user/:id"))
+                .with_substitutes(vec![("id", "1")])
+                .disable_encoding()
+                .with_query_params(vec![("a b", "c d")]),
+        );
+        urls.push(
+            FormatUrl::new("https://x.com")
+                .with_path_template(UriTemplate::parse("/user/{id}{?page}").unwrap())
+                .with_substitutes(vec![("id", "1"), ("page", "2")]),
+        );
+        urls
+    }
+
+    #[test]
+    fn write_url_matches_joined_test() {
+        for url in configurations() {
+            let mut written = String::new();
+            url.write_url(&mut written).unwrap();
+            assert_eq!(written, url.format_joined());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn write_url_nested_query_test() {
+        use crate::ArrayFormat;
+
+        #[derive(serde::Serialize)]
+        struct Query {
+            filter: Filter,
+            tags: Vec<&'static str>,
+        }
+
+        #[derive(serde::Serialize)]
+        struct Filter {
+            state: &'static str,
+        }
+
+        for array_format in [
+            ArrayFormat::Brackets,
+            ArrayFormat::Indices,
+            ArrayFormat::Repeat,
+            ArrayFormat::Comma,
+        ] {
+            let url = FormatUrl::new("https://x.com?a=b")
+                .with_array_format(array_format)
+                .with_query(&Query {
+                    filter: Filter { state: "open" },
+                    tags: vec!["x", "y"],
+                });
+            assert_eq!(url.to_string(), url.format_joined(), "{array_format:?}");
+        }
+    }
+}
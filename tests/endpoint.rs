@@ -0,0 +1,136 @@
+#![cfg(feature = "macros")]
+
+use std::fmt;
+
+use format_url::{ArrayFormat, Endpoint};
+
+#[derive(Clone, Copy)]
+enum State {
+    Open,
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            State::Open => f.write_str("open"),
+        }
+    }
+}
+
+#[derive(Endpoint)]
+#[endpoint(path = "/repos/:owner/:repo/issues")]
+struct ListIssues {
+    #[endpoint(path)]
+    owner: String,
+    #[endpoint(path)]
+    repo: String,
+    #[endpoint(query)]
+    state: Option<State>,
+    #[endpoint(query)]
+    labels: Vec<&'static str>,
+    #[endpoint(query, rename = "per_page")]
+    page_size: u32,
+    #[allow(dead_code)]
+    body: String,
+}
+
+fn list_issues() -> ListIssues {
+    ListIssues {
+        owner: "alextes".to_string(),
+        repo: "format-url".to_string(),
+        state: Some(State::Open),
+        labels: vec!["bug", "help wanted"],
+        page_size: 50,
+        body: String::new(),
+    }
+}
+
+#[test]
+fn format_url_test() {
+    assert_eq!(
+        list_issues().format_url("https://api.github.com"),
+        "https://api.github.com/repos/alextes/format-url/issues\
+         ?state=open&labels[]=bug&labels[]=help%20wanted&per_page=50"
+    );
+}
+
+#[test]
+fn to_format_url_test() {
+    let request = ListIssues {
+        state: None,
+        ..list_issues()
+    };
+    assert_eq!(
+        request
+            .to_format_url("https://api.github.com/")
+            .with_array_format(ArrayFormat::Comma)
+            .try_format_url(),
+        Ok("https://api.github.com/repos/alextes/format-url/issues\
+            ?labels=bug,help%20wanted&per_page=50"
+            .to_string())
+    );
+}
+
+#[derive(Endpoint)]
+#[endpoint(path = "/users/:user_id")]
+struct GetUser<T: fmt::Display> {
+    #[endpoint(path, rename = "user_id")]
+    id: T,
+}
+
+#[test]
+fn rename_and_generics_test() {
+    assert_eq!(
+        GetUser { id: 7 }.format_url("https://api.x.com"),
+        "https://api.x.com/users/7"
+    );
+}
+
+#[derive(Endpoint)]
+#[endpoint(path = "/status")]
+struct Status {}
+
+#[test]
+fn no_fields_test() {
+    assert_eq!(
+        Status {}.format_url("https://api.x.com"),
+        "https://api.x.com/status"
+    );
+}
+
+#[derive(Endpoint)]
+#[endpoint(path = r"/orgs/:org(/projects/:project)/:tab?/:id=1/12\:30")]
+struct GetProject {
+    #[endpoint(path)]
+    org: &'static str,
+    #[endpoint(path)]
+    project: Option<&'static str>,
+    #[endpoint(path)]
+    tab: Option<&'static str>,
+    #[endpoint(path)]
+    id: Option<u32>,
+}
+
+#[test]
+fn optional_path_fields_test() {
+    let request = GetProject {
+        org: "acme",
+        project: None,
+        tab: None,
+        id: None,
+    };
+    assert_eq!(
+        request.format_url("https://api.x.com"),
+        "https://api.x.com/orgs/acme/1/12:30"
+    );
+    let request = GetProject {
+        project: Some("rocket"),
+        tab: Some("issues"),
+        id: Some(7),
+        ..request
+    };
+    assert_eq!(
+        request.format_url("https://api.x.com"),
+        "https://api.x.com/orgs/acme/projects/rocket/issues/7/12:30"
+    );
+}
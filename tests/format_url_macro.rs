@@ -0,0 +1,76 @@
+#![cfg(feature = "macros")]
+
+use format_url::{format_url, PreEncoded};
+
+#[test]
+fn named_arguments_test() {
+    struct User {
+        id: u32,
+    }
+    let user = User { id: 42 };
+
+    assert_eq!(
+        format_url!(
+            "https://api.x.com/user/{id}?active={active}",
+            id = user.id,
+            active = true,
+        ),
+        "https://api.x.com/user/42?active=true"
+    );
+}
+
+#[test]
+fn captured_variables_test() {
+    let org = "alex tes";
+    let page = 2;
+    assert_eq!(
+        format_url!("https://api.x.com/orgs/{org}/repos?page={page}&org={org}"),
+        "https://api.x.com/orgs/alex%20tes/repos?page=2&org=alex%20tes"
+    );
+}
+
+#[test]
+fn encoding_matches_format_url_test() {
+    let name = "a/b?c";
+    assert_eq!(
+        format_url!("https://api.x.com/{name}?name={name}"),
+        format_url::FormatUrl::new("https://api.x.com")
+            .with_path_template("/:name")
+            .with_substitutes(vec![("name", name)])
+            .with_query_params(vec![("name", name)])
+            .format_url()
+    );
+}
+
+#[test]
+fn arguments_evaluated_once_test() {
+    let mut calls = 0;
+    let mut next = || {
+        calls += 1;
+        calls
+    };
+    assert_eq!(
+        format_url!("/{a}/{a}?b={b}", b = next(), a = next()),
+        "/2/2?b=1"
+    );
+}
+
+#[test]
+fn escaped_braces_test() {
+    assert_eq!(format_url!("/{{literal}}"), "/{literal}");
+}
+
+#[test]
+fn url_placeholder_test() {
+    let url = "x";
+    assert_eq!(format_url!("/{url}"), "/x");
+}
+
+#[test]
+fn pre_encoded_test() {
+    let cursor = PreEncoded("a%2Bb");
+    assert_eq!(
+        format_url!("/events/{cursor}?after={cursor}&q={q}", q = "a+b"),
+        "/events/a%2Bb?after=a%2Bb&q=a%2Bb"
+    );
+}
@@ -0,0 +1,80 @@
+#![cfg(feature = "reqwest")]
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::thread::{self, JoinHandle};
+
+use format_url::{ClientExt, FormatUrl, FormatUrlError};
+use reqwest::{Client, Method};
+
+/// Accept a single request and return its request line, such as `GET /a?b=c HTTP/1.1`.
+fn serve_once() -> (String, JoinHandle<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).unwrap();
+
+        let mut header = String::new();
+        while reader.read_line(&mut header).unwrap() > 2 {
+            header.clear();
+        }
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nconnection: close\r\n\r\n")
+            .unwrap();
+
+        request_line.trim_end().to_string()
+    });
+
+    (base, handle)
+}
+
+#[tokio::test]
+async fn get_formatted_test() {
+    let (base, server) = serve_once();
+
+    let response = Client::new()
+        .get_formatted(
+            FormatUrl::new(&base)
+                .with_path_template("/user/:name")
+                .with_substitutes(vec![("name", "alex tes")])
+                .with_query_params(vec![("active", "true")]),
+        )
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), 204);
+    assert_eq!(
+        server.join().unwrap(),
+        "GET /user/alex%20tes?active=true HTTP/1.1"
+    );
+}
+
+#[tokio::test]
+async fn into_request_test() {
+    let (base, server) = serve_once();
+
+    FormatUrl::new(&base)
+        .with_path_template("/items")
+        .into_request(Method::DELETE, &Client::new())
+        .unwrap()
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(server.join().unwrap(), "DELETE /items HTTP/1.1");
+}
+
+#[test]
+fn invalid_url_test() {
+    assert!(matches!(
+        Client::new()
+            .get_formatted(FormatUrl::new("http://localhost").with_path_template("/user/:name")),
+        Err(FormatUrlError::MissingSubstitute { .. })
+    ));
+}
@@ -0,0 +1,85 @@
+//! Runs the official uritemplate-test suite against [`UriTemplate`].
+
+use std::fs;
+
+use format_url::{TemplateValue, UriTemplate};
+use serde_json::Value;
+
+fn to_string(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn to_template_value(value: &Value) -> Option<TemplateValue> {
+    match value {
+        Value::Null => None,
+        Value::Array(values) => Some(TemplateValue::List(values.iter().map(to_string).collect())),
+        Value::Object(pairs) => Some(TemplateValue::AssocList(
+            pairs
+                .iter()
+                .map(|(key, value)| (key.clone(), to_string(value)))
+                .collect(),
+        )),
+        other => Some(TemplateValue::String(to_string(other))),
+    }
+}
+
+fn run_fixture(name: &str) {
+    let path = format!(
+        "{}/tests/fixtures/uritemplate-test/{name}",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let fixture: Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+
+    for (group, suite) in fixture.as_object().unwrap() {
+        let variables = suite["variables"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .filter_map(|(key, value)| Some((key.as_str(), to_template_value(value)?)))
+            .collect::<Vec<_>>();
+
+        for testcase in suite["testcases"].as_array().unwrap() {
+            let template = testcase[0].as_str().unwrap();
+            let expanded = UriTemplate::parse(template)
+                .and_then(|template| template.try_expand(&variables))
+                .ok();
+
+            match &testcase[1] {
+                Value::Bool(false) => assert_eq!(expanded, None, "{group}: {template}"),
+                Value::String(expected) => {
+                    assert_eq!(expanded.as_ref(), Some(expected), "{group}: {template}")
+                }
+                Value::Array(options) => assert!(
+                    options
+                        .iter()
+                        .any(|option| Some(option.as_str().unwrap()) == expanded.as_deref()),
+                    "{group}: {template} expanded to {expanded:?}"
+                ),
+                other => panic!("unexpected expectation {other}"),
+            }
+        }
+    }
+}
+
+#[test]
+fn spec_examples_test() {
+    run_fixture("spec-examples.json");
+}
+
+#[test]
+fn spec_examples_by_section_test() {
+    run_fixture("spec-examples-by-section.json");
+}
+
+#[test]
+fn extended_tests_test() {
+    run_fixture("extended-tests.json");
+}
+
+#[test]
+fn negative_tests_test() {
+    run_fixture("negative-tests.json");
+}